@@ -0,0 +1,461 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Mul, Neg, Shl, Shr, Sub};
+
+// Each limb holds nine decimal digits, which keeps parsing and printing simple
+const BASE: u64 = 1_000_000_000;
+
+// An integer of any size. The limbs go from the least significant one and never end
+// in a zero, so zero has no limbs and is never negative.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt { negative: false, limbs: Vec::new() }
+    }
+
+    pub fn from_i64(value: i64) -> BigInt {
+        BigInt::from_i128(value as i128)
+    }
+
+    pub fn from_i128(value: i128) -> BigInt {
+        let mut rest = value.unsigned_abs();
+        let mut limbs = Vec::new();
+        while rest > 0 {
+            limbs.push((rest % BASE as u128) as u32);
+            rest /= BASE as u128;
+        }
+
+        BigInt { negative: value < 0, limbs }
+    }
+
+    // Accepts an optional sign followed by digits in the given base
+    pub fn parse(text: &str, radix: u32) -> Option<BigInt> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut value = BigInt::zero();
+        for c in digits.chars() {
+            let digit = c.to_digit(radix)?;
+            value = &value.times_small(radix as u64) + &BigInt::from_i64(digit as i64);
+        }
+        value.negative = negative;
+
+        Some(value.normalized())
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_i128().and_then(|value| i64::try_from(value).ok())
+    }
+
+    pub fn to_i128(&self) -> Option<i128> {
+        let mut value: i128 = 0;
+        for &limb in self.limbs.iter().rev() {
+            value = value.checked_mul(BASE as i128)?.checked_add(limb as i128)?;
+        }
+
+        Some(if self.negative { -value } else { value })
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().expect("Bad integer")
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    // Quotient truncated towards zero, the remainder has the sign of the dividend
+    pub fn div_rem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        assert!(!other.is_zero(), "Division by zero");
+
+        let mut quotient = vec![0; self.limbs.len()];
+        let mut remainder = BigInt::zero();
+        let divisor = other.abs();
+
+        for i in (0..self.limbs.len()).rev() {
+            remainder.limbs.insert(0, self.limbs[i]);
+            remainder = remainder.normalized();
+
+            // The largest digit whose product with the divisor still fits
+            let (mut low, mut high) = (0, BASE - 1);
+            while low < high {
+                let middle = (low + high).div_ceil(2);
+                if divisor.times_small(middle).cmp_magnitude(&remainder) == Ordering::Greater {
+                    high = middle - 1;
+                } else {
+                    low = middle;
+                }
+            }
+
+            quotient[i] = low as u32;
+            remainder = &remainder - &divisor.times_small(low);
+        }
+
+        let quotient = BigInt { negative: self.negative != other.negative, limbs: quotient };
+        remainder.negative = self.negative;
+
+        (quotient.normalized(), remainder.normalized())
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from_i64(1);
+        let mut base = self.clone();
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let (mut a, mut b) = (self.abs(), other.abs());
+        while !b.is_zero() {
+            let remainder = a.div_rem(&b).1;
+            a = b;
+            b = remainder;
+        }
+        a
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt { negative: false, limbs: self.limbs.clone() }
+    }
+
+    // The two's complement in words of 32 bits, from the least significant one,
+    // extended with copies of the sign up to the given length
+    fn to_words(&self, len: usize) -> Vec<u32> {
+        let modulus = BigInt::from_i64(1 << 32);
+        // The complement of a negative number is the inverse of the bits of |n| - 1
+        let mut rest = if self.negative { &self.abs() - &BigInt::from_i64(1) } else { self.clone() };
+
+        let mut words = Vec::with_capacity(len);
+        while words.len() < len {
+            let (quotient, remainder) = rest.div_rem(&modulus);
+            let word = remainder.to_i64().expect("Bad word") as u32;
+            words.push(if self.negative { !word } else { word });
+            rest = quotient;
+        }
+        words
+    }
+
+    fn from_words(words: &[u32]) -> BigInt {
+        let negative = words.last().is_some_and(|word| word >> 31 == 1);
+
+        let mut magnitude = BigInt::zero();
+        for &word in words.iter().rev() {
+            let word = if negative { !word } else { word };
+            magnitude = &magnitude.times_small(1 << 32) + &BigInt::from_i64(word as i64);
+        }
+
+        if negative {
+            -&(&magnitude + &BigInt::from_i64(1))
+        } else {
+            magnitude
+        }
+    }
+
+    fn bitwise(&self, other: &BigInt, op: impl Fn(u32, u32) -> u32) -> BigInt {
+        // Each limb has less than 30 bits, one more word leaves room for the sign
+        let len = self.limbs.len().max(other.limbs.len()) * 30 / 32 + 2;
+        let words: Vec<u32> = self.to_words(len).into_iter().zip(other.to_words(len)).map(|(a, b)| op(a, b)).collect();
+
+        BigInt::from_words(&words)
+    }
+
+    fn times_small(&self, factor: u64) -> BigInt {
+        let mut limbs = Vec::with_capacity(self.limbs.len() + 1);
+        let mut carry = 0;
+        for &limb in &self.limbs {
+            let product = limb as u64 * factor + carry;
+            limbs.push((product % BASE) as u32);
+            carry = product / BASE;
+        }
+        while carry > 0 {
+            limbs.push((carry % BASE) as u32);
+            carry /= BASE;
+        }
+
+        BigInt { negative: false, limbs }.normalized()
+    }
+
+    fn normalized(mut self) -> BigInt {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+        self
+    }
+
+    fn cmp_magnitude(&self, other: &BigInt) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+
+    fn add_magnitude(&self, other: &BigInt) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(self.limbs.len().max(other.limbs.len()) + 1);
+        let mut carry = 0;
+        for i in 0..self.limbs.len().max(other.limbs.len()) {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64 + *other.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push((sum % BASE) as u32);
+            carry = sum / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry as u32);
+        }
+        limbs
+    }
+
+    // The magnitude of self must not be smaller than the one of other
+    fn sub_magnitude(&self, other: &BigInt) -> Vec<u32> {
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0;
+        for i in 0..self.limbs.len() {
+            let mut difference = self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if difference < 0 {
+                difference += BASE as i64;
+                borrow = 1;
+            }
+            limbs.push(difference as u32);
+        }
+        limbs
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt { negative: self.negative, limbs: self.add_magnitude(other) }.normalized();
+        }
+
+        match self.cmp_magnitude(other) {
+            Ordering::Less => BigInt { negative: other.negative, limbs: other.sub_magnitude(self) }.normalized(),
+            _ => BigInt { negative: self.negative, limbs: self.sub_magnitude(other) }.normalized(),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut limbs = vec![0u64; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let product = limbs[i + j] + a as u64 * b as u64 + carry;
+                limbs[i + j] = product % BASE;
+                carry = product / BASE;
+            }
+            limbs[i + other.limbs.len()] += carry;
+        }
+
+        BigInt {
+            negative: self.negative != other.negative,
+            limbs: limbs.into_iter().map(|limb| limb as u32).collect(),
+        }
+        .normalized()
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt { negative: !self.negative, limbs: self.limbs.clone() }.normalized()
+    }
+}
+
+// The bit operations work on the two's complement, as if the sign went on forever
+impl BitAnd for &BigInt {
+    type Output = BigInt;
+
+    fn bitand(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a & b)
+    }
+}
+
+impl BitOr for &BigInt {
+    type Output = BigInt;
+
+    fn bitor(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a | b)
+    }
+}
+
+impl BitXor for &BigInt {
+    type Output = BigInt;
+
+    fn bitxor(self, other: &BigInt) -> BigInt {
+        self.bitwise(other, |a, b| a ^ b)
+    }
+}
+
+impl Shl<u32> for &BigInt {
+    type Output = BigInt;
+
+    fn shl(self, count: u32) -> BigInt {
+        self.mul(&BigInt::from_i64(2).pow(count))
+    }
+}
+
+// Rounds towards minus infinity, like shifting the two's complement does
+impl Shr<u32> for &BigInt {
+    type Output = BigInt;
+
+    fn shr(self, count: u32) -> BigInt {
+        let (quotient, remainder) = self.div_rem(&BigInt::from_i64(2).pow(count));
+        if remainder.is_negative() {
+            quotient.sub(&BigInt::from_i64(1))
+        } else {
+            quotient
+        }
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some(last) = self.limbs.last() else {
+            return write!(f, "0");
+        };
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", last)?;
+        for limb in self.limbs.iter().rev().skip(1) {
+            write!(f, "{:09}", limb)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text, 10).expect("Bad test integer")
+    }
+
+    fn div_rem(dividend: &str, divisor: &str) -> (String, String) {
+        let (quotient, remainder) = big(dividend).div_rem(&big(divisor));
+        (quotient.to_string(), remainder.to_string())
+    }
+
+    #[test]
+    fn div_rem_truncates_towards_zero() {
+        assert_eq!(div_rem("7", "2"), ("3".to_string(), "1".to_string()));
+        assert_eq!(div_rem("-7", "2"), ("-3".to_string(), "-1".to_string()));
+        assert_eq!(div_rem("7", "-2"), ("-3".to_string(), "1".to_string()));
+        assert_eq!(div_rem("-7", "-2"), ("3".to_string(), "-1".to_string()));
+        assert_eq!(div_rem("-6", "3"), ("-2".to_string(), "0".to_string()));
+        assert_eq!(div_rem("1", "1000000000000"), ("0".to_string(), "1".to_string()));
+    }
+
+    #[test]
+    fn div_rem_of_several_limbs() {
+        let (quotient, remainder) = div_rem("-1000000000000000000000000000007", "1000000000000003");
+        assert_eq!(quotient, "-999999999999997");
+        assert_eq!(remainder, "-16");
+    }
+
+    #[test]
+    fn gcd_is_never_negative() {
+        assert_eq!(big("12").gcd(&big("-18")).to_string(), "6");
+        assert_eq!(big("-12").gcd(&big("-18")).to_string(), "6");
+        assert_eq!(big("0").gcd(&big("-5")).to_string(), "5");
+        assert_eq!(big("0").gcd(&big("0")).to_string(), "0");
+        assert_eq!(big("123456789012345678901234567890").gcd(&big("987654321098765432109876543210")).to_string(), "9000000000900000000090");
+    }
+
+    #[test]
+    fn words_round_trip() {
+        for text in ["0", "1", "-1", "-2", "4294967295", "-4294967296", "-4294967297", "-123456789012345678901234567890"] {
+            let value = big(text);
+            let words = value.to_words(value.limbs.len() + 2);
+            assert_eq!(BigInt::from_words(&words).to_string(), text);
+        }
+    }
+
+    #[test]
+    fn negative_words_are_twos_complement() {
+        assert_eq!(big("-1").to_words(2), vec![u32::MAX, u32::MAX]);
+        assert_eq!(big("-4294967296").to_words(3), vec![0, u32::MAX, u32::MAX]);
+    }
+
+    #[test]
+    fn bit_operations_match_i128() {
+        let values = [0i128, 1, -1, 5, -6, 1 << 40, -(1 << 70) + 3, i64::MAX as i128 + 1];
+        for a in values {
+            for b in values {
+                let (x, y) = (BigInt::from_i128(a), BigInt::from_i128(b));
+                assert_eq!((&x & &y).to_i128(), Some(a & b));
+                assert_eq!((&x | &y).to_i128(), Some(a | b));
+                assert_eq!((&x ^ &y).to_i128(), Some(a ^ b));
+            }
+        }
+    }
+
+    #[test]
+    fn shr_rounds_towards_minus_infinity() {
+        assert_eq!((&big("7") >> 1).to_string(), "3");
+        assert_eq!((&big("-7") >> 1).to_string(), "-4");
+        assert_eq!((&big("-8") >> 1).to_string(), "-4");
+        assert_eq!((&big("-1") >> 100).to_string(), "-1");
+        assert_eq!((&big("-1267650600228229401496703205377") >> 100).to_string(), "-2");
+        assert_eq!((&big("1") << 100).to_string(), "1267650600228229401496703205376");
+    }
+}
@@ -1,14 +1,35 @@
-use std::collections::HashMap;
+use crate::interpreter::errors::runtime_error;
+use crate::interpreter::errors;
+use crate::interpreter::errors::ErrorKind;
+use crate::interpreter::errors::RuntimeError;
 use crate::interpreter::expressions::Expression;
+use crate::interpreter::expressions::IntOp;
+use crate::interpreter::integers;
 use crate::interpreter::memory;
+use crate::interpreter::memory::Memory;
+use crate::interpreter::values::Value;
+use crate::interpreter::values::Selector;
 
 #[derive (Clone)]
 pub enum Command {
   Block(Vec::<Command>),
   Assignment(AssignCmd),
+  MultiAssignment(Vec::<AssignCmd>),
+  Declaration(AssignCmd),
   If(IfCmd),
+  Case(CaseCmd),
   While(WhileCmd),
-  Output(Expression)
+  For(ForCmd),
+  ForIn(ForInCmd),
+  Repeat(RepeatCmd),
+  Output(Expression),
+  Call(Expression),
+  Return(Expression),
+  Break(Option::<String>),
+  Continue(Option::<String>),
+  Try(TryCmd),
+  Raise(Expression),
+  Check(Contract)
 }
 
 impl std::fmt::Debug for Command {
@@ -16,9 +37,22 @@ impl std::fmt::Debug for Command {
     match self {
       Command::Block(_b) => write!(f, "Block"),
       Command::Assignment(_a) => write!(f,"Assignment"),
+      Command::MultiAssignment(_m) => write!(f,"MultiAssignment"),
+      Command::Declaration(_d) => write!(f,"Declaration"),
       Command::If(_i) => write!(f,"If"),
+      Command::Case(_c) => write!(f,"Case"),
       Command::While(_w) => write!(f,"While"),
-      Command::Output(_o) => write!(f,"Output")
+      Command::For(_f) => write!(f,"For"),
+      Command::ForIn(_f) => write!(f,"ForIn"),
+      Command::Repeat(_r) => write!(f,"Repeat"),
+      Command::Output(_o) => write!(f,"Output"),
+      Command::Call(_c) => write!(f,"Call"),
+      Command::Return(_r) => write!(f,"Return"),
+      Command::Break(_b) => write!(f,"Break"),
+      Command::Continue(_c) => write!(f,"Continue"),
+      Command::Try(_t) => write!(f,"Try"),
+      Command::Raise(_r) => write!(f,"Raise"),
+      Command::Check(_c) => write!(f,"Check")
     }
   }
 }
@@ -26,52 +60,404 @@ impl std::fmt::Debug for Command {
 #[derive (Clone)]
 pub struct AssignCmd {
   pub expr: Expression,
-  pub var: String
+  pub var: String,
+  // Array indexes and record fields leading to the part of the variable being assigned
+  pub path: Vec::<Accessor>,
+  // Operator of compound assignments like +=, combining the old value with expr
+  pub op: Option::<IntOp>,
+  pub line_number: u16
+}
+
+#[derive (Clone)]
+pub enum Accessor {
+  Index(Expression),
+  Field(String)
+}
+
+impl AssignCmd {
+  // Evaluates the indexes and the value to be stored, in that order
+  fn eval(&self, mem: &mut Memory) -> (Vec::<Selector>,Value) {
+    let path: Vec::<Selector> = self.path.iter().map(|accessor| match accessor {
+      Accessor::Index(index) => Selector::Index(index.exec(mem)),
+      Accessor::Field(name) => Selector::Field(name.clone())
+    }).collect();
+    let mut result = self.expr.exec(mem);
+
+    if let Some(op) = self.op {
+      let current = if path.is_empty() {
+        memory::read(mem,&self.var,self.line_number)
+      }
+      else {
+        memory::read_at(mem,&self.var,&path,self.line_number)
+      };
+      result = op.apply(current,result,self.line_number);
+    }
+
+    (path,result)
+  }
+
+  fn store(&self, mem: &mut Memory, path: &[Selector], result: Value) {
+    if path.is_empty() {
+      memory::write(mem,&self.var,result);
+    }
+    else {
+      memory::write_at(mem,&self.var,path,result,self.line_number);
+    }
+  }
 }
 
 #[derive (Clone)]
 pub struct IfCmd {
   pub condition: Expression,
   pub then_cmds: Vec::<Command>,
+  // Tried in order when the condition is false
+  pub elifs: Vec::<ElifBranch>,
   pub else_cmds: Vec::<Command> 
 }
 
+#[derive (Clone)]
+pub struct ElifBranch {
+  pub condition: Expression,
+  pub cmds: Vec::<Command>,
+}
+
+#[derive (Clone)]
+pub struct CaseCmd {
+  pub expr: Expression,
+  // Enum of the arm values, whose variants are matched by ordinal, None for integers
+  pub kind: Option::<String>,
+  pub arms: Vec::<CaseArm>,
+  pub else_cmds: Vec::<Command>,
+  pub line_number: u16
+}
+
+// Runs when the value is inside any of the inclusive ranges, a single value v is the range v..v
+#[derive (Clone)]
+pub struct CaseArm {
+  pub ranges: Vec::<(i128,i128)>,
+  pub cmds: Vec::<Command>,
+}
+
 #[derive (Clone)]
 pub struct WhileCmd {
   pub cond: Expression,
+  // Checked before every evaluation of the condition
+  pub invariants: Vec::<Contract>,
   pub cmds: Vec::<Command>,
+  pub label: Option::<String>,
+}
+
+// A condition that must hold: an assert, an invariant, a requires or an ensures
+#[derive (Clone)]
+pub struct Contract {
+  // How the condition is called in the error
+  pub clause: &'static str,
+  pub cond: Expression,
+  pub message: Option::<String>,
+  pub line_number: u16
+}
+
+impl Contract {
+  // A violation shows the values of the variables the condition reads
+  fn check(&self, mem: &mut Memory) {
+    if self.cond.exec(mem).as_bool(self.line_number) {
+      return;
+    }
+
+    let mut names = Vec::<String>::new();
+    self.cond.variables(&mut names);
+
+    let values: Vec::<String> = names.iter()
+      .filter_map(|name| memory::find(mem,name).map(|value| format!("{} = {}",name,value)))
+      .collect();
+
+    let mut msg = format!("{} falhou",self.clause);
+    if let Some(message) = &self.message {
+      msg = format!("{}: {}",msg,message);
+    }
+    if !values.is_empty() {
+      msg = format!("{} ({})",msg,values.join(", "));
+    }
+
+    runtime_error(ErrorKind::Contract,self.line_number,&msg);
+  }
+}
+
+#[derive (Clone)]
+pub struct ForCmd {
+  pub var: String,
+  pub start: Expression,
+  pub end: Expression,
+  pub step: Expression,
+  pub cmds: Vec::<Command>,
+  pub label: Option::<String>,
+  pub line_number: u16
+}
+
+// Goes over the keys of a map or the elements of an array
+#[derive (Clone)]
+pub struct ForInCmd {
+  pub var: String,
+  pub expr: Expression,
+  pub cmds: Vec::<Command>,
+  pub label: Option::<String>,
+  pub line_number: u16
+}
+
+#[derive (Clone)]
+pub struct RepeatCmd {
+  pub cmds: Vec::<Command>,
+  pub cond: Expression,
+  pub label: Option::<String>,
+}
+
+// The handler runs with var holding the error raised by the body, finally runs
+// after both, even when they end with an error, return, break or continue
+#[derive (Clone)]
+pub struct TryCmd {
+  pub body: Vec::<Command>,
+  pub var: String,
+  pub handler: Vec::<Command>,
+  pub finally: Vec::<Command>,
+}
+
+pub struct Function {
+  pub name: String,
+  pub params: Vec::<String>,
+  pub body: Vec::<Command>,
+}
+
+// How the execution of a command list ended. Break and continue carry the label
+// of the loop they refer to, or None for the innermost one.
+pub enum Flow {
+  Normal,
+  Return(Value),
+  Break(Option::<String>),
+  Continue(Option::<String>)
+}
+
+// Decides what a loop does after its body ended with the given flow: None to go on
+// with the next iteration, or the flow that ends the loop itself
+fn loop_end(flow: Flow, label: &Option::<String>) -> Option::<Flow> {
+  let targets = |target: &Option::<String>| target.is_none() || target == label;
+
+  match flow {
+    Flow::Normal => None,
+    Flow::Continue(ref target) if targets(target) => None,
+    Flow::Break(ref target) if targets(target) => Some(Flow::Normal),
+    other => Some(other)
+  }
 }
 
-pub fn cmd_exec(list: Vec::<Command>, map: &mut HashMap::<String,i32>){
+// Runs the commands of a block, the variables declared in it are dropped at the end
+pub fn block_exec(list: &[Command], mem: &mut Memory) -> Flow {
+  memory::push_scope(mem);
+  let flow = cmd_exec(list,mem);
+  memory::pop_scope(mem);
+
+  flow
+}
+
+pub fn cmd_exec(list: &[Command], mem: &mut Memory) -> Flow {
   //println!("{:?}",list);
   
   for cmd in list {
     
     match cmd{
       Command::Assignment(asg) => {
-        let result = asg.expr.exec(map);
-        memory::write(map,&asg.var,result);
+        let (path,result) = asg.eval(mem);
+        asg.store(mem,&path,result);
+      },
+      Command::MultiAssignment(asgs) => {
+        // Every right side is evaluated before any variable changes
+        let results: Vec::<(Vec::<Selector>,Value)> = asgs.iter().map(|asg| asg.eval(mem)).collect();
+
+        for (asg,(path,result)) in asgs.iter().zip(results) {
+          asg.store(mem,&path,result);
+        }
+      },
+      Command::Declaration(d) => {
+        let result = d.expr.exec(mem);
+        memory::declare(mem,&d.var,result);
       },
       Command::Block(b) => {
-        cmd_exec(b,map);
+        let flow = cmd_exec(b,mem);
+
+        if !matches!(flow, Flow::Normal) {
+          return flow;
+        }
       },
       Command::If(i) => {
-        if i.condition.exec(map) != 0 {
-          cmd_exec(i.then_cmds,map);
+        let mut cmds = &i.else_cmds;
+
+        if i.condition.exec(mem).as_bool(i.condition.line()) {
+          cmds = &i.then_cmds;
+        }
+        else if let Some(elif) = i.elifs.iter().find(|e| e.condition.exec(mem).as_bool(e.condition.line())) {
+          cmds = &elif.cmds;
+        }
+
+        let flow = block_exec(cmds,mem);
+        if !matches!(flow, Flow::Normal) {
+          return flow;
         }
-        else {
-          cmd_exec(i.else_cmds,map);
+      },
+      Command::Case(c) => {
+        let value = match (&c.kind,c.expr.exec(mem)) {
+          (Some(kind),Value::Enum(variant)) if variant.ty == *kind => variant.ordinal as i128,
+          (Some(kind),value) => runtime_error(ErrorKind::Type,c.line_number,&format!("Esperada uma variante de {} em vez de {}",kind,value.kind())),
+          (None,value) => value.as_wide(c.line_number)
+        };
+        let arm = c.arms.iter().find(|arm| arm.ranges.iter().any(|(low,high)| *low <= value && value <= *high));
+
+        let cmds = match arm {
+          Some(arm) => &arm.cmds,
+          None => &c.else_cmds
+        };
+
+        let flow = block_exec(cmds,mem);
+        if !matches!(flow, Flow::Normal) {
+          return flow;
         }
       },
       Command::Output(o) => {
-        println!("{}",o.exec(map));
+        println!("{}",o.exec(mem));
       },
       Command::While(w) => {
         
-        while w.cond.exec(map) != 0 {
-      cmd_exec(w.cmds.clone(),map);
+        loop {
+          for invariant in &w.invariants {
+            invariant.check(mem);
+          }
+          if !w.cond.exec(mem).as_bool(w.cond.line()) {
+            break;
+          }
+
+          if let Some(flow) = loop_end(block_exec(&w.cmds,mem),&w.label) {
+            if !matches!(flow, Flow::Normal) {
+              return flow;
+            }
+            break;
+          }
+        }
+      },
+      Command::For(f) => {
+        // Wide enough for unsigned 64 bit bounds, and for stepping past them
+        let start = f.start.exec(mem).as_wide(f.line_number);
+        let end = f.end.exec(mem).as_wide(f.line_number);
+        let step = f.step.exec(mem).as_wide(f.line_number);
+
+        if step == 0 {
+          runtime_error(ErrorKind::Value,f.line_number,"O passo do for não pode ser zero");
         }
+
+        // The bounds are evaluated once, the loop variable only exists inside the loop
+        memory::push_scope(mem);
+        let mut i = start;
+        let mut result = Flow::Normal;
+
+        while (step > 0 && i <= end) || (step < 0 && i >= end) {
+          memory::declare(mem,&f.var,integers::int(i));
+
+          if let Some(flow) = loop_end(block_exec(&f.cmds,mem),&f.label) {
+            result = flow;
+            break;
+          }
+          i += step;
+        }
+        memory::pop_scope(mem);
+
+        if !matches!(result, Flow::Normal) {
+          return result;
+        }
+      },
+      Command::ForIn(f) => {
+        // Changes to the collection inside the loop don't change what is visited
+        let items = match f.expr.exec(mem) {
+          Value::Map(map) => map.keys(),
+          Value::Array(items) => items,
+          other => runtime_error(ErrorKind::Type,f.line_number,&format!("Não é possível percorrer {}",other.kind()))
+        };
+
+        memory::push_scope(mem);
+        let mut result = Flow::Normal;
+
+        for item in items {
+          memory::declare(mem,&f.var,item);
+
+          if let Some(flow) = loop_end(block_exec(&f.cmds,mem),&f.label) {
+            result = flow;
+            break;
+          }
+        }
+        memory::pop_scope(mem);
+
+        if !matches!(result, Flow::Normal) {
+          return result;
+        }
+      },
+      Command::Repeat(r) => {
+        // The condition sees the variables declared in the body
+        loop {
+          memory::push_scope(mem);
+          let end = loop_end(cmd_exec(&r.cmds,mem),&r.label);
+          let done = end.is_some() || r.cond.exec(mem).as_bool(r.cond.line());
+          memory::pop_scope(mem);
+
+          match end {
+            Some(Flow::Normal) => break,
+            Some(flow) => return flow,
+            None if done => break,
+            None => {}
+          }
+        }
+      },
+      Command::Call(c) => {
+        c.exec(mem);
+      },
+      Command::Return(r) => {
+        return Flow::Return(r.exec(mem));
+      },
+      Command::Break(label) => {
+        return Flow::Break(label.clone());
+      },
+      Command::Continue(label) => {
+        return Flow::Continue(label.clone());
+      },
+      Command::Try(t) => {
+        let mark = memory::mark(mem);
+        let mut result = errors::catch(|| block_exec(&t.body,mem));
+
+        if let Err(error) = result {
+          // The scopes and frames left by the error are dropped
+          memory::restore(mem,mark);
+          memory::push_scope(mem);
+          memory::declare(mem,&t.var,error.to_value());
+
+          result = errors::catch(|| cmd_exec(&t.handler,mem));
+          memory::restore(mem,mark);
+        }
+
+        let after = block_exec(&t.finally,mem);
+        if !matches!(after, Flow::Normal) {
+          return after;
+        }
+
+        match result {
+          Ok(Flow::Normal) => {},
+          Ok(flow) => return flow,
+          Err(error) => errors::raise(error)
+        }
+      },
+      Command::Raise(r) => {
+        let value = r.exec(mem);
+        errors::raise(RuntimeError::from_value(&value,r.line()));
+      },
+      Command::Check(c) => {
+        c.check(mem);
       }
     }
   }
-}
\ No newline at end of file
+
+  Flow::Normal
+}
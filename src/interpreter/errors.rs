@@ -0,0 +1,151 @@
+use std::cell::Cell;
+use std::panic;
+
+use crate::interpreter::values::Record;
+use crate::interpreter::values::Value;
+use crate::lexical::sources;
+
+// What went wrong, seen by the programs as the kind field of the error
+#[derive(Clone, Copy)]
+pub enum ErrorKind {
+    Type,
+    Value,
+    Index,
+    Key,
+    UndefinedVariable,
+    BadInput,
+    DivisionByZero,
+    NegativeExponent,
+    Overflow,
+    CallDepth,
+    Raised,
+    Contract,
+}
+
+impl ErrorKind {
+    const ALL: [ErrorKind; 12] = [
+        ErrorKind::Type,
+        ErrorKind::Value,
+        ErrorKind::Index,
+        ErrorKind::Key,
+        ErrorKind::UndefinedVariable,
+        ErrorKind::BadInput,
+        ErrorKind::DivisionByZero,
+        ErrorKind::NegativeExponent,
+        ErrorKind::Overflow,
+        ErrorKind::CallDepth,
+        ErrorKind::Raised,
+        ErrorKind::Contract,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Type => "type",
+            ErrorKind::Value => "value",
+            ErrorKind::Index => "index",
+            ErrorKind::Key => "key",
+            ErrorKind::UndefinedVariable => "undefined_variable",
+            ErrorKind::BadInput => "bad_input",
+            ErrorKind::DivisionByZero => "division_by_zero",
+            ErrorKind::NegativeExponent => "negative_exponent",
+            ErrorKind::Overflow => "overflow",
+            ErrorKind::CallDepth => "call_depth",
+            ErrorKind::Raised => "raised",
+            ErrorKind::Contract => "contract",
+        }
+    }
+}
+
+// An error found while running the program, which a try may catch
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    // The value given to raise, 0 for the errors of the interpreter
+    pub code: i64,
+    pub line: u16,
+    pub message: String,
+}
+
+// Fields of the record a catch receives, declared for every program
+pub const ERROR_RECORD: &str = "Error";
+pub const ERROR_FIELDS: [&str; 4] = ["kind", "code", "line", "message"];
+
+impl RuntimeError {
+    pub fn to_value(&self) -> Value {
+        let (_, line) = sources::split(self.line);
+        let values = [
+            Value::Str(self.kind.name().to_string()),
+            Value::Int(self.code),
+            Value::Int(line as i64),
+            Value::Str(self.message.clone()),
+        ];
+
+        Value::Record(Record {
+            name: ERROR_RECORD.to_string(),
+            fields: ERROR_FIELDS.iter().map(|field| field.to_string()).zip(values).collect(),
+        })
+    }
+
+    // The error given to raise: a code, or a caught error raised again from another line
+    pub fn from_value(value: &Value, line: u16) -> RuntimeError {
+        if let Value::Record(record) = value {
+            if record.name == ERROR_RECORD {
+                let kind = value.field("kind", line).as_str(line);
+
+                return RuntimeError {
+                    kind: ErrorKind::ALL
+                        .into_iter()
+                        .find(|k| k.name() == kind)
+                        .unwrap_or(ErrorKind::Raised),
+                    code: value.field("code", line).as_int(line),
+                    line,
+                    message: value.field("message", line).as_str(line).to_string(),
+                };
+            }
+        }
+
+        let code = value.as_int(line);
+        RuntimeError {
+            kind: ErrorKind::Raised,
+            code,
+            line,
+            message: format!("Erro {} lançado", code),
+        }
+    }
+}
+
+thread_local! {
+    // Number of try blocks being run
+    static TRYING: Cell<usize> = const { Cell::new(0) };
+}
+
+// Aborts the program reporting an error found while running it, unless a try catches it
+pub fn runtime_error(kind: ErrorKind, line: u16, msg: &str) -> ! {
+    raise(RuntimeError {
+        kind,
+        code: 0,
+        line,
+        message: msg.to_string(),
+    })
+}
+
+pub fn raise(error: RuntimeError) -> ! {
+    // Unwinding goes back to the innermost try, without running the panic hook
+    if TRYING.with(|trying| trying.get()) > 0 {
+        panic::resume_unwind(Box::new(error));
+    }
+
+    println!("{}:: Erro de execução: {}", sources::location(error.line), error.message);
+    std::process::exit(1);
+}
+
+// Runs f, returning the error it raised instead of aborting the program
+pub fn catch<R>(f: impl FnOnce() -> R) -> Result<R, RuntimeError> {
+    TRYING.with(|trying| trying.set(trying.get() + 1));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+    TRYING.with(|trying| trying.set(trying.get() - 1));
+
+    result.map_err(|payload| match payload.downcast::<RuntimeError>() {
+        Ok(error) => *error,
+        Err(payload) => panic::resume_unwind(payload),
+    })
+}
@@ -1,19 +1,36 @@
+use crate::interpreter::commands::{cmd_exec, Flow};
+use crate::interpreter::errors::runtime_error;
+use crate::interpreter::errors::ErrorKind;
+use crate::interpreter::integers;
+use crate::interpreter::bigint::BigInt;
 use crate::interpreter::memory;
-use std::collections::HashMap;
+use crate::interpreter::memory::Memory;
+use crate::interpreter::values::Map;
+use crate::interpreter::values::Record;
+use crate::interpreter::values::Selector;
+use crate::interpreter::values::Value;
+
+// Calls nested deeper than this are reported instead of overflowing the stack
+pub const MAX_CALL_DEPTH: usize = 1000;
 
 #[derive(Copy, Clone)]
 pub enum ExprType {
     // Int
     ConstInt,
-    NegInt,
     ReadInt,
-    BinaryInt,
     Var,
 
+    // Float
+    ConstFloat,
+
+    // String
+    ConstStr,
+
     // Bool
     ConstBool,
-    SingleBool,
-    NotBool,
+
+    // Enum
+    ConstEnum,
 }
 
 #[derive(Copy, Clone)]
@@ -24,6 +41,21 @@ pub enum IntOp {
     Div,
     Mod,
     Pow,
+    // Exact division, giving a rational
+    Ratio,
+    BitAnd,
+    BitOr,
+    Xor,
+    ShiftLeft,
+    // Copies the sign bit, while the logical shift brings in zeros
+    ShiftRight,
+    LogicalShiftRight,
+}
+
+#[derive(Copy, Clone)]
+pub enum UnaryOp {
+    Neg,
+    BitNot,
 }
 
 #[derive(Copy, Clone)]
@@ -36,48 +68,296 @@ pub enum BoolOp {
     GreaterEqual,
 }
 
+#[derive(Copy, Clone)]
+pub enum LogicOp {
+    And,
+    Or,
+}
+
+#[derive(Copy, Clone)]
+pub enum BuiltinFunc {
+    Array,
+    Len,
+    Substr,
+    Str,
+    Int,
+    Float,
+    Has,
+    Remove,
+    Keys,
+    Rational,
+}
+
+impl BuiltinFunc {
+    // Returns the builtin with that name and its number of arguments
+    pub fn from_name(name: &str) -> Option<(BuiltinFunc, usize)> {
+        match name {
+            "array" => Some((BuiltinFunc::Array, 1)),
+            "len" => Some((BuiltinFunc::Len, 1)),
+            "substr" => Some((BuiltinFunc::Substr, 3)),
+            "str" => Some((BuiltinFunc::Str, 1)),
+            "int" => Some((BuiltinFunc::Int, 1)),
+            "float" => Some((BuiltinFunc::Float, 1)),
+            "has" => Some((BuiltinFunc::Has, 2)),
+            "remove" => Some((BuiltinFunc::Remove, 2)),
+            "keys" => Some((BuiltinFunc::Keys, 1)),
+            "rational" => Some((BuiltinFunc::Rational, 2)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Expression {
     Ex(Expr),
+    Un(UnaryInt),
     Bi(BinaryInt),
     Sb(SingleBool),
+    Bb(BinaryBool),
+    Nb(NotBool),
+    Fc(FuncCall),
+    Bt(Builtin),
+    Al(ArrayLit),
+    Ix(Index),
+    Cd(Conditional),
+    Rl(RecordLit),
+    Fd(Field),
+    Ml(MapLit),
 }
 
 #[derive(Clone)]
 pub struct Expr {
     pub expr: ExprType,
-    pub value: i32,
+    pub value: Value,
     pub name: String,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct UnaryInt {
+    pub op: UnaryOp,
+    pub expr: Box<Expression>,
+    pub line_number: u16,
 }
 
 #[derive(Clone)]
 pub struct BinaryInt {
-    pub left: Expr,
+    pub left: Box<Expression>,
     pub op: IntOp,
-    pub right: Expr,
+    pub right: Box<Expression>,
+    pub line_number: u16,
 }
 
 #[derive(Clone)]
 pub struct SingleBool {
-    pub left: Expr,
+    pub left: Box<Expression>,
     pub op: BoolOp,
-    pub right: Expr,
+    pub right: Box<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct BinaryBool {
+    pub left: Box<Expression>,
+    pub op: LogicOp,
+    pub right: Box<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct NotBool {
+    pub expr: Box<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct FuncCall {
+    pub name: String,
+    pub args: Vec<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct Builtin {
+    pub func: BuiltinFunc,
+    pub args: Vec<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct ArrayLit {
+    pub items: Vec<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct MapLit {
+    pub entries: Vec<(Expression, Expression)>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct RecordLit {
+    pub name: String,
+    // Field names in the order of the declaration, one for each argument
+    pub fields: Vec<String>,
+    pub args: Vec<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct Field {
+    pub record: Box<Expression>,
+    pub name: String,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct Index {
+    pub array: Box<Expression>,
+    pub index: Box<Expression>,
+    pub line_number: u16,
+}
+
+#[derive(Clone)]
+pub struct Conditional {
+    pub cond: Box<Expression>,
+    pub then_expr: Box<Expression>,
+    pub else_expr: Box<Expression>,
+    pub line_number: u16,
 }
 
 impl Expression {
-    pub fn exec(&self, map: &mut HashMap<String, i32>) -> i32 {
+    pub fn exec(&self, mem: &mut Memory) -> Value {
         match self {
-            Expression::Ex(expr) => expr.exec(map),
-            Expression::Bi(binary_int) => binary_int.exec(map),
-            Expression::Sb(single_bool) => single_bool.exec(map),
+            Expression::Ex(expr) => expr.exec(mem),
+            Expression::Un(unary_int) => unary_int.exec(mem),
+            Expression::Bi(binary_int) => binary_int.exec(mem),
+            Expression::Sb(single_bool) => single_bool.exec(mem),
+            Expression::Bb(binary_bool) => binary_bool.exec(mem),
+            Expression::Nb(not_bool) => not_bool.exec(mem),
+            Expression::Fc(func_call) => func_call.exec(mem),
+            Expression::Bt(builtin) => builtin.exec(mem),
+            Expression::Al(array_lit) => array_lit.exec(mem),
+            Expression::Ix(index) => index.exec(mem),
+            Expression::Rl(record_lit) => record_lit.exec(mem),
+            Expression::Fd(field) => field.exec(mem),
+            Expression::Ml(map_lit) => map_lit.exec(mem),
+            Expression::Cd(conditional) => conditional.exec(mem),
+        }
+    }
+
+    pub fn line(&self) -> u16 {
+        match self {
+            Expression::Ex(expr) => expr.line_number,
+            Expression::Un(unary_int) => unary_int.line_number,
+            Expression::Bi(binary_int) => binary_int.line_number,
+            Expression::Sb(single_bool) => single_bool.line_number,
+            Expression::Bb(binary_bool) => binary_bool.line_number,
+            Expression::Nb(not_bool) => not_bool.line_number,
+            Expression::Fc(func_call) => func_call.line_number,
+            Expression::Bt(builtin) => builtin.line_number,
+            Expression::Al(array_lit) => array_lit.line_number,
+            Expression::Ix(index) => index.line_number,
+            Expression::Rl(record_lit) => record_lit.line_number,
+            Expression::Fd(field) => field.line_number,
+            Expression::Ml(map_lit) => map_lit.line_number,
+            Expression::Cd(conditional) => conditional.line_number,
+        }
+    }
+
+    // Runs f over the value of the expression, variables are borrowed instead of copied
+    fn with_value<R>(&self, mem: &mut Memory, f: impl FnOnce(&Value) -> R) -> R {
+        match self {
+            Expression::Ex(expr) if matches!(expr.expr, ExprType::Var) => {
+                f(memory::lookup(mem, &expr.name, expr.line_number))
+            }
+            _ => f(&self.exec(mem)),
+        }
+    }
+
+    // Adds the names of the variables the expression reads, each one once
+    pub fn variables(&self, names: &mut Vec<String>) {
+        let mut visit = |expr: &Expression| expr.variables(names);
+
+        match self {
+            Expression::Ex(expr) => {
+                if matches!(expr.expr, ExprType::Var) && !names.contains(&expr.name) {
+                    names.push(expr.name.clone());
+                }
+            }
+            Expression::Un(un) => visit(&un.expr),
+            Expression::Nb(nb) => visit(&nb.expr),
+            Expression::Bi(bi) => {
+                visit(&bi.left);
+                visit(&bi.right);
+            }
+            Expression::Sb(sb) => {
+                visit(&sb.left);
+                visit(&sb.right);
+            }
+            Expression::Bb(bb) => {
+                visit(&bb.left);
+                visit(&bb.right);
+            }
+            Expression::Fc(fc) => fc.args.iter().for_each(visit),
+            Expression::Bt(bt) => bt.args.iter().for_each(visit),
+            Expression::Al(al) => al.items.iter().for_each(visit),
+            Expression::Rl(rl) => rl.args.iter().for_each(visit),
+            Expression::Ix(ix) => {
+                visit(&ix.array);
+                visit(&ix.index);
+            }
+            Expression::Fd(fd) => visit(&fd.record),
+            Expression::Ml(ml) => ml.entries.iter().for_each(|(key, value)| {
+                visit(key);
+                visit(value);
+            }),
+            Expression::Cd(cd) => {
+                visit(&cd.cond);
+                visit(&cd.then_expr);
+                visit(&cd.else_expr);
+            }
+        }
+    }
+
+    // Whether the expression names a variable or a part of one, that can be changed in place
+    pub fn is_place(&self) -> bool {
+        match self {
+            Expression::Ex(expr) => matches!(expr.expr, ExprType::Var),
+            Expression::Ix(index) => index.array.is_place(),
+            Expression::Fd(field) => field.record.is_place(),
+            _ => false,
+        }
+    }
+
+    // Evaluates the variable name and the path to the part of it the expression names, see is_place
+    fn place(&self, mem: &mut Memory) -> (String, Vec<Selector>) {
+        match self {
+            Expression::Ex(expr) => (expr.name.clone(), Vec::new()),
+            Expression::Ix(index) => {
+                let (name, mut path) = index.array.place(mem);
+                path.push(Selector::Index(index.index.exec(mem)));
+                (name, path)
+            }
+            Expression::Fd(field) => {
+                let (name, mut path) = field.record.place(mem);
+                path.push(Selector::Field(field.name.clone()));
+                (name, path)
+            }
+            _ => unreachable!(),
         }
     }
 }
 
 impl Expr {
-    fn exec(&self, map: &mut HashMap<String, i32>) -> i32 {
+    fn exec(&self, mem: &mut Memory) -> Value {
         match self.expr {
-            ExprType::ConstBool | ExprType::ConstInt => self.value,
+            ExprType::ConstBool
+            | ExprType::ConstInt
+            | ExprType::ConstFloat
+            | ExprType::ConstStr
+            | ExprType::ConstEnum => self.value.clone(),
             ExprType::ReadInt => {
                 let sin = std::io::stdin();
                 let mut s = String::new();
@@ -85,41 +365,537 @@ impl Expr {
                 sin.read_line(&mut s).expect("Failed to read line");
                 s = s.trim().to_string();
 
-                s.parse::<i32>().expect("Bad input")
+                match (integers::parse(&s, 10), s.parse::<f64>()) {
+                    (Some(value), _) => value,
+                    (None, _) if BigInt::parse(&s, 10).is_some() => runtime_error(
+                        ErrorKind::BadInput,
+                        self.line_number,
+                        &format!("Inteiro {} não cabe em {}", s, integers::describe()),
+                    ),
+                    (_, Ok(value)) => Value::Float(value),
+                    _ => runtime_error(
+                        ErrorKind::BadInput,
+                        self.line_number,
+                        &format!("Entrada \"{}\" inválida", s),
+                    ),
+                }
             }
-            ExprType::NegInt => -self.value,
-            ExprType::NotBool => !self.value,
-            ExprType::Var => memory::read(map, &self.name),
-            _ => panic!("Invalid type"),
+            ExprType::Var => memory::read(mem, &self.name, self.line_number),
         }
     }
 }
 
-impl BinaryInt {
-    fn exec(&self, map: &mut HashMap<String, i32>) -> i32 {
+impl UnaryInt {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let value = self.expr.exec(mem);
+
         match self.op {
-            IntOp::Add => self.left.exec(map) + self.right.exec(map),
-            IntOp::Sub => self.left.exec(map) - self.right.exec(map),
-            IntOp::Mul => self.left.exec(map) * self.right.exec(map),
-            IntOp::Div => self.left.exec(map) / self.right.exec(map),
-            IntOp::Mod => self.left.exec(map) % self.right.exec(map),
-            IntOp::Pow => self
-                .left
-                .exec(map)
-                .pow(self.right.exec(map).try_into().unwrap()),
+            UnaryOp::Neg => match value {
+                Value::Float(value) => Value::Float(-value),
+                Value::Int(value) if !integers::unbounded() => {
+                    integers::narrow(-(value as i128), self.line_number, || format!("-({})", value))
+                }
+                value => IntOp::Sub.apply(Value::Int(0), value, self.line_number),
+            },
+            // The complement of n is -n - 1 in two's complement
+            UnaryOp::BitNot if integers::unbounded() => {
+                let value = value.as_big(self.line_number);
+                Value::from_big(&-&value - &BigInt::from_i64(1))
+            }
+            UnaryOp::BitNot => integers::wrap(!value.as_wide(self.line_number)),
         }
     }
 }
 
+impl IntOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            IntOp::Add => "+",
+            IntOp::Sub => "-",
+            IntOp::Mul => "*",
+            IntOp::Div => "/",
+            IntOp::Mod => "%",
+            IntOp::Pow => "^",
+            IntOp::Ratio => "//",
+            IntOp::BitAnd => "&",
+            IntOp::BitOr => "|",
+            IntOp::Xor => "xor",
+            IntOp::ShiftLeft => "<<",
+            IntOp::ShiftRight => ">>",
+            IntOp::LogicalShiftRight => ">>>",
+        }
+    }
+
+    pub fn is_bitwise(self) -> bool {
+        matches!(
+            self,
+            IntOp::BitAnd | IntOp::BitOr | IntOp::Xor | IntOp::ShiftLeft | IntOp::ShiftRight | IntOp::LogicalShiftRight
+        )
+    }
+
+    // Also used by the compound assignments
+    pub fn apply(self, left: Value, right: Value, line: u16) -> Value {
+        if let (IntOp::Add, Value::Str(left)) = (self, &left) {
+            return Value::Str(format!("{}{}", left, right.as_str(line)));
+        }
+
+        if self.is_bitwise() {
+            return self.apply_bits(left, right, line);
+        }
+
+        // Mixed operands are promoted to float
+        if matches!(left, Value::Float(_)) || matches!(right, Value::Float(_)) {
+            let left = left.as_float(line);
+            let right = right.as_float(line);
+
+            return Value::Float(match self {
+                IntOp::Add => left + right,
+                IntOp::Sub => left - right,
+                IntOp::Mul => left * right,
+                IntOp::Div | IntOp::Ratio => left / right,
+                IntOp::Mod => left % right,
+                IntOp::Pow => left.powf(right),
+                _ => unreachable!(),
+            });
+        }
+
+        if matches!(self, IntOp::Ratio) || matches!(left, Value::Rational(_)) || matches!(right, Value::Rational(_)) {
+            return self.apply_rational(left, right, line);
+        }
+
+        if integers::unbounded() {
+            return self.apply_big(left, right, line);
+        }
+
+        let left = left.as_wide(line);
+        let right = right.as_wide(line);
+        let operation = || format!("{} {} {}", left, self.symbol(), right);
+
+        self.check_operands(right == 0, right < 0, line, operation);
+
+        // Sums and differences of two operands of up to 64 bits always fit, products only
+        // when signed. Division and remainder truncate towards zero, like the machine does.
+        let exact = match self {
+            IntOp::Add => left + right,
+            IntOp::Sub => left - right,
+            IntOp::Mul => integers::mul(left, right),
+            IntOp::Div => left / right,
+            IntOp::Mod => left % right,
+            IntOp::Pow => integers::pow(left, right),
+            _ => unreachable!(),
+        };
+
+        integers::narrow(exact, line, operation)
+    }
+
+    // Bit operations only take integers, in two's complement. A left shift is a
+    // multiplication by a power of two, so it overflows like one.
+    fn apply_bits(self, left: Value, right: Value, line: u16) -> Value {
+        if integers::unbounded() {
+            return self.apply_big_bits(left, right, line);
+        }
+
+        let left = left.as_wide(line);
+        let right = right.as_wide(line);
+        let operation = || format!("{} {} {}", left, self.symbol(), right);
+
+        if matches!(self, IntOp::ShiftLeft | IntOp::ShiftRight | IntOp::LogicalShiftRight) && right < 0 {
+            runtime_error(ErrorKind::Value, line, &format!("Deslocamento negativo em {}", operation()));
+        }
+
+        // Operands fit in 65 bits, so shifting by less than 64 fits in 128
+        let count = right.min(127) as u32;
+        match self {
+            IntOp::BitAnd => integers::wrap(left & right),
+            IntOp::BitOr => integers::wrap(left | right),
+            IntOp::Xor => integers::wrap(left ^ right),
+            IntOp::ShiftLeft => {
+                // Any other integer overflows from 64 on, and keeps no bits when wrapped
+                let exact = if count < 64 { left << count } else { left.signum() << 100 };
+                integers::narrow(exact, line, operation)
+            }
+            IntOp::ShiftRight => integers::wrap(left >> count),
+            IntOp::LogicalShiftRight => integers::wrap(left.rem_euclid(1 << integers::width()) >> count),
+            _ => unreachable!(),
+        }
+    }
+
+    // Without a width there are no bits to bring in from the left
+    fn apply_big_bits(self, left: Value, right: Value, line: u16) -> Value {
+        let left = left.as_big(line);
+        let right = right.as_big(line);
+        let operation = || format!("{} {} {}", left, self.symbol(), right);
+
+        let count = || match right.to_i64().map(u32::try_from) {
+            Some(Ok(count)) => count,
+            _ if right.is_negative() => runtime_error(ErrorKind::Value, line, &format!("Deslocamento negativo em {}", operation())),
+            _ => runtime_error(ErrorKind::Value, line, &format!("Deslocamento grande demais em {}", operation())),
+        };
+
+        Value::from_big(match self {
+            IntOp::BitAnd => &left & &right,
+            IntOp::BitOr => &left | &right,
+            IntOp::Xor => &left ^ &right,
+            IntOp::ShiftLeft => &left << count(),
+            IntOp::ShiftRight => &left >> count(),
+            IntOp::LogicalShiftRight if left.is_negative() => runtime_error(
+                ErrorKind::Value,
+                line,
+                &format!("Deslocamento lógico de um inteiro negativo requer --int-width em {}", operation()),
+            ),
+            IntOp::LogicalShiftRight => &left >> count(),
+            _ => unreachable!(),
+        })
+    }
+
+    // Integer division by zero and negative integer exponents have no result
+    fn check_operands(self, zero: bool, negative: bool, line: u16, operation: impl Fn() -> String) {
+        if matches!(self, IntOp::Div | IntOp::Mod | IntOp::Ratio) && zero {
+            runtime_error(ErrorKind::DivisionByZero, line, &format!("Divisão por zero em {}", operation()));
+        }
+        if matches!(self, IntOp::Pow) && negative {
+            runtime_error(ErrorKind::NegativeExponent, line, &format!("Expoente negativo em {}", operation()));
+        }
+    }
+
+    // Results that don't fit in 64 bits become big integers
+    fn apply_big(self, left: Value, right: Value, line: u16) -> Value {
+        if let (Value::Int(left), Value::Int(right)) = (&left, &right) {
+            let result = match self {
+                IntOp::Add => left.checked_add(*right),
+                IntOp::Sub => left.checked_sub(*right),
+                IntOp::Mul => left.checked_mul(*right),
+                IntOp::Div => left.checked_div(*right),
+                IntOp::Mod => left.checked_rem(*right),
+                IntOp::Pow => u32::try_from(*right).ok().and_then(|right| left.checked_pow(right)),
+                _ => unreachable!(),
+            };
+            if let Some(result) = result {
+                return Value::Int(result);
+            }
+        }
+
+        let left = left.as_big(line);
+        let right = right.as_big(line);
+        let operation = || format!("{} {} {}", left, self.symbol(), right);
+
+        self.check_operands(right.is_zero(), right.is_negative(), line, operation);
+
+        Value::from_big(match self {
+            IntOp::Add => &left + &right,
+            IntOp::Sub => &left - &right,
+            IntOp::Mul => &left * &right,
+            IntOp::Div => left.div_rem(&right).0,
+            IntOp::Mod => left.div_rem(&right).1,
+            IntOp::Pow => match right.to_i64().and_then(|right| u32::try_from(right).ok()) {
+                Some(right) => left.pow(right),
+                None => runtime_error(ErrorKind::Value, line, &format!("Expoente {} grande demais", right)),
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    // Integers are promoted to rationals, the exponent of a power must be an integer
+    fn apply_rational(self, left: Value, right: Value, line: u16) -> Value {
+        let operation = || format!("{} {} {}", left, self.symbol(), right);
+        let a = left.as_rational(line);
+
+        let result = if let IntOp::Pow = self {
+            let exponent = match i32::try_from(right.as_int(line)) {
+                Ok(exponent) => exponent,
+                Err(_) => runtime_error(ErrorKind::Value, line, &format!("Expoente {} grande demais", right)),
+            };
+            if exponent < 0 && a.is_zero() {
+                runtime_error(ErrorKind::DivisionByZero, line, &format!("Divisão por zero em {}", operation()));
+            }
+            a.pow(exponent)
+        } else {
+            let b = right.as_rational(line);
+            self.check_operands(b.is_zero(), false, line, operation);
+
+            match self {
+                IntOp::Add => &a + &b,
+                IntOp::Sub => &a - &b,
+                IntOp::Mul => &a * &b,
+                IntOp::Div | IntOp::Ratio => &a / &b,
+                IntOp::Mod => &a % &b,
+                _ => unreachable!(),
+            }
+        };
+
+        // Fractions equal to an integer become that integer
+        match result.to_integer() {
+            Some(integer) => integers::from_big(integer.clone(), line, operation),
+            None => Value::Rational(result),
+        }
+    }
+}
+
+impl BinaryInt {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let left = self.left.exec(mem);
+        let right = self.right.exec(mem);
+
+        self.op.apply(left, right, self.line_number)
+    }
+}
+
 impl SingleBool {
-    fn exec(&self, map: &mut HashMap<String, i32>) -> i32 {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let left = self.left.exec(mem);
+        let right = self.right.exec(mem);
+
+        // Strings are compared in lexicographic order
+        if let (Value::Str(left), Value::Str(right)) = (&left, &right) {
+            return Value::Bool(self.compare(left, right));
+        }
+
+        let floats = matches!(left, Value::Float(_)) || matches!(right, Value::Float(_));
+        if floats && left.is_number() && right.is_number() {
+            let left = left.as_float(self.line_number);
+            let right = right.as_float(self.line_number);
+
+            return Value::Bool(self.compare(&left, &right));
+        }
+
+        match self.op {
+            BoolOp::Equal => return Value::Bool(left == right),
+            BoolOp::NotEqual => return Value::Bool(left != right),
+            _ => {}
+        }
+
+        if matches!(left, Value::Rational(_)) || matches!(right, Value::Rational(_)) {
+            return Value::Bool(self.compare(&left.as_rational(self.line_number), &right.as_rational(self.line_number)));
+        }
+        if matches!(left, Value::Big(_)) || matches!(right, Value::Big(_)) {
+            return Value::Bool(self.compare(&left.as_big(self.line_number), &right.as_big(self.line_number)));
+        }
+
+        Value::Bool(self.compare(&left.as_int(self.line_number), &right.as_int(self.line_number)))
+    }
+
+    fn compare<T: PartialEq + PartialOrd + ?Sized>(&self, left: &T, right: &T) -> bool {
+        match self.op {
+            BoolOp::Equal => left == right,
+            BoolOp::NotEqual => left != right,
+            BoolOp::Greater => left > right,
+            BoolOp::Lower => left < right,
+            BoolOp::GreaterEqual => left >= right,
+            BoolOp::LowerEqual => left <= right,
+        }
+    }
+}
+
+impl BinaryBool {
+    // The right side is only evaluated when the left one does not decide the result
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let left = self.left.exec(mem).as_bool(self.line_number);
+
         match self.op {
-            BoolOp::Equal => (self.left.exec(map) == self.right.exec(map)) as i32,
-            BoolOp::NotEqual => (self.left.exec(map) != self.right.exec(map)) as i32,
-            BoolOp::Greater => (self.left.exec(map) > self.right.exec(map)) as i32,
-            BoolOp::Lower => (self.left.exec(map) < self.right.exec(map)) as i32,
-            BoolOp::GreaterEqual => (self.left.exec(map) >= self.right.exec(map)) as i32,
-            BoolOp::LowerEqual => (self.left.exec(map) <= self.right.exec(map)) as i32,
+            LogicOp::And => Value::Bool(left && self.right.exec(mem).as_bool(self.line_number)),
+            LogicOp::Or => Value::Bool(left || self.right.exec(mem).as_bool(self.line_number)),
+        }
+    }
+}
+
+impl NotBool {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        Value::Bool(!self.expr.exec(mem).as_bool(self.line_number))
+    }
+}
+
+impl FuncCall {
+    // Arguments are evaluated in the caller frame, the body runs in a new one
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let function = memory::function(mem, &self.name);
+        let args: Vec<Value> = self.args.iter().map(|arg| arg.exec(mem)).collect();
+
+        if memory::depth(mem) >= MAX_CALL_DEPTH {
+            runtime_error(
+                ErrorKind::CallDepth,
+                self.line_number,
+                &format!("Profundidade máxima de chamadas excedida ao chamar {}", function.name),
+            );
+        }
+
+        memory::push_frame(mem);
+        for (param, value) in function.params.iter().zip(args) {
+            memory::write(mem, param, value);
+        }
+
+        let result = match cmd_exec(&function.body, mem) {
+            Flow::Return(value) => value,
+            _ => Value::Int(0),
+        };
+        memory::pop_frame(mem);
+
+        result
+    }
+}
+
+impl Builtin {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let line = self.line_number;
+
+        match self.func {
+            BuiltinFunc::Array => {
+                let size = self.args[0].exec(mem).as_int(line);
+                if size < 0 {
+                    runtime_error(ErrorKind::Value, line, &format!("Tamanho de vetor {} inválido", size));
+                }
+
+                // Sizes too large for the memory are an error of the program, not of the interpreter
+                let mut items = Vec::new();
+                if items.try_reserve_exact(size as usize).is_err() {
+                    runtime_error(ErrorKind::Value, line, &format!("Memória insuficiente para um vetor de tamanho {}", size));
+                }
+                items.resize(size as usize, Value::Int(0));
+
+                Value::Array(items)
+            }
+            BuiltinFunc::Len => {
+                let len = self.args[0].with_value(mem, |value| match value {
+                    Value::Array(items) => items.len(),
+                    Value::Map(map) => map.len(),
+                    Value::Str(text) => text.chars().count(),
+                    _ => runtime_error(ErrorKind::Type, line, &format!("Não é possível obter o tamanho de {}", value.kind())),
+                });
+                // Lengths are integers of the program too, so they must fit in its width
+                integers::narrow(len as i128, line, || format!("len resultando em {}", len))
+            }
+            // substr(text, start, count) counts characters, not bytes
+            BuiltinFunc::Substr => {
+                let text = self.args[0].exec(mem);
+                let start = self.args[1].exec(mem).as_int(line);
+                let count = self.args[2].exec(mem).as_int(line);
+                let chars: Vec<char> = text.as_str(line).chars().collect();
+
+                if start < 0 || count < 0 || start as usize + count as usize > chars.len() {
+                    runtime_error(
+                        ErrorKind::Index,
+                        line,
+                        &format!(
+                            "Substring ({}, {}) fora dos limites de uma string de tamanho {}",
+                            start,
+                            count,
+                            chars.len()
+                        ),
+                    );
+                }
+
+                Value::Str(chars[start as usize..start as usize + count as usize].iter().collect())
+            }
+            BuiltinFunc::Str => Value::Str(self.args[0].exec(mem).to_string()),
+            // Floats are truncated towards zero
+            BuiltinFunc::Int => match self.args[0].exec(mem) {
+                value @ (Value::Int(_) | Value::Big(_)) => value,
+                Value::Rational(value) => integers::from_big(value.truncate(), line, || format!("int({})", value)),
+                Value::Float(value) => integers::narrow(value as i128, line, || format!("int({:?})", value)),
+                value => {
+                    let text = value.as_str(line).trim();
+
+                    match integers::parse(text, 10) {
+                        Some(value) => value,
+                        None => runtime_error(ErrorKind::Value, line, &format!("Inteiro \"{}\" inválido", text)),
+                    }
+                }
+            },
+            BuiltinFunc::Rational => {
+                let numerator = self.args[0].exec(mem);
+                let denominator = self.args[1].exec(mem);
+                IntOp::Ratio.apply(numerator, denominator, line)
+            }
+            BuiltinFunc::Float => match self.args[0].exec(mem) {
+                Value::Str(text) => match text.trim().parse::<f64>() {
+                    Ok(value) => Value::Float(value),
+                    Err(_) => runtime_error(ErrorKind::Value, line, &format!("Float \"{}\" inválido", text.trim())),
+                },
+                value => Value::Float(value.as_float(line)),
+            },
+            BuiltinFunc::Has => {
+                let key = self.args[1].exec(mem);
+                self.args[0].with_value(mem, |map| Value::Bool(map.as_map(line).get(&key).is_some()))
+            }
+            // Changes the map in place, returning the value that was removed
+            BuiltinFunc::Remove => {
+                let (name, path) = self.args[0].place(mem);
+                let key = self.args[1].exec(mem);
+
+                let mut map = memory::lookup_mut(mem, &name, line);
+                for selector in &path {
+                    map = map.select_mut(selector, line);
+                }
+                let removed = map.as_map_mut(line).remove(&key);
+
+                match removed {
+                    Some(value) => value,
+                    None => runtime_error(ErrorKind::Key, line, &format!("Chave {} não encontrada no mapa", key)),
+                }
+            }
+            BuiltinFunc::Keys => self.args[0].with_value(mem, |map| Value::Array(map.as_map(line).keys())),
+        }
+    }
+}
+
+impl ArrayLit {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        Value::Array(self.items.iter().map(|item| item.exec(mem)).collect())
+    }
+}
+
+impl Index {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let selector = Selector::Index(self.index.exec(mem));
+
+        self.array
+            .with_value(mem, |array| array.select(&selector, self.line_number).clone())
+    }
+}
+
+impl MapLit {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let mut map = Map::default();
+
+        for (key, value) in &self.entries {
+            let key = key.exec(mem);
+            Map::check_key(&key, self.line_number);
+            map.insert(key, value.exec(mem));
+        }
+
+        Value::Map(Box::new(map))
+    }
+}
+
+impl RecordLit {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        let fields = self
+            .fields
+            .iter()
+            .zip(&self.args)
+            .map(|(name, arg)| (name.clone(), arg.exec(mem)))
+            .collect();
+
+        Value::Record(Record {
+            name: self.name.clone(),
+            fields,
+        })
+    }
+}
+
+impl Field {
+    fn exec(&self, mem: &mut Memory) -> Value {
+        self.record
+            .with_value(mem, |record| record.field(&self.name, self.line_number).clone())
+    }
+}
+
+impl Conditional {
+    // Only the selected branch is evaluated
+    fn exec(&self, mem: &mut Memory) -> Value {
+        if self.cond.exec(mem).as_bool(self.line_number) {
+            self.then_expr.exec(mem)
+        } else {
+            self.else_expr.exec(mem)
         }
     }
 }
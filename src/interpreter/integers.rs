@@ -0,0 +1,370 @@
+use std::cell::Cell;
+
+use crate::interpreter::bigint::BigInt;
+use crate::interpreter::errors::runtime_error;
+use crate::interpreter::errors::ErrorKind;
+use crate::interpreter::values::Value;
+
+// What happens to a result that does not fit in the integers, set by --overflow
+#[derive(Clone, Copy, PartialEq)]
+pub enum Overflow {
+    // Keeps the lower bits, like the machine does
+    Wrap,
+    // Gives the closest integer that fits
+    Saturate,
+    Error,
+}
+
+impl Overflow {
+    pub fn from_name(name: &str) -> Option<Overflow> {
+        match name {
+            "wrap" => Some(Overflow::Wrap),
+            "saturate" => Some(Overflow::Saturate),
+            "error" => Some(Overflow::Error),
+            _ => None,
+        }
+    }
+}
+
+thread_local! {
+    // Whether integers grow without limit instead of overflowing, set by --bigint
+    static UNBOUNDED: Cell<bool> = const { Cell::new(false) };
+    static OVERFLOW: Cell<Overflow> = const { Cell::new(Overflow::Error) };
+    // Bits of the integers and whether they have a sign, set by --int-width and --unsigned
+    static WIDTH: Cell<u32> = const { Cell::new(32) };
+    static UNSIGNED: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_unbounded(unbounded: bool) {
+    UNBOUNDED.with(|cell| cell.set(unbounded));
+}
+
+pub fn unbounded() -> bool {
+    UNBOUNDED.with(|cell| cell.get())
+}
+
+pub fn set_overflow(overflow: Overflow) {
+    OVERFLOW.with(|cell| cell.set(overflow));
+}
+
+pub fn overflow() -> Overflow {
+    OVERFLOW.with(|cell| cell.get())
+}
+
+pub fn set_width(width: u32) {
+    WIDTH.with(|cell| cell.set(width));
+}
+
+pub fn width() -> u32 {
+    WIDTH.with(|cell| cell.get())
+}
+
+pub fn set_unsigned(unsigned: bool) {
+    UNSIGNED.with(|cell| cell.set(unsigned));
+}
+
+pub fn unsigned() -> bool {
+    UNSIGNED.with(|cell| cell.get())
+}
+
+// The integers of the program, for the errors
+pub fn describe() -> String {
+    if unsigned() {
+        format!("{} bits sem sinal", width())
+    } else {
+        format!("{} bits", width())
+    }
+}
+
+// Smallest and largest integer of the width, in two's complement when signed
+fn range() -> (i128, i128) {
+    let width = width();
+    if unsigned() {
+        (0, (1 << width) - 1)
+    } else {
+        (-(1 << (width - 1)), (1 << (width - 1)) - 1)
+    }
+}
+
+// Reads an integer written in the given base, None when it is not one or does not fit
+pub fn parse(text: &str, radix: u32) -> Option<Value> {
+    if unbounded() {
+        return BigInt::parse(text, radix).map(Value::from_big);
+    }
+
+    let value = i128::from_str_radix(text, radix).ok()?;
+    let (min, max) = range();
+    (min..=max).contains(&value).then(|| int(value))
+}
+
+// Hexadecimal, binary and octal literals write the bits of the integer, so any of them
+// that fits in the width is taken in two's complement: 0xFF is -1 with --int-width=8
+pub fn parse_bits(text: &str, radix: u32) -> Option<Value> {
+    if unbounded() {
+        return parse(text, radix);
+    }
+
+    let value = i128::from_str_radix(text, radix).ok()?;
+    (0..1 << width()).contains(&value).then(|| wrap(value))
+}
+
+// Only unsigned 64 bit integers may not fit in an Int
+pub fn int(value: i128) -> Value {
+    match i64::try_from(value) {
+        Ok(value) => Value::Int(value),
+        Err(_) => Value::Big(BigInt::from_i128(value)),
+    }
+}
+
+// Fits the exact result of an operation in the integers. The operation, with its
+// operands, is only written when the result overflows.
+pub fn narrow(exact: i128, line: u16, operation: impl Fn() -> String) -> Value {
+    let (min, max) = range();
+    if (min..=max).contains(&exact) {
+        return int(exact);
+    }
+
+    match overflow() {
+        Overflow::Wrap => wrap(exact),
+        Overflow::Saturate => int(exact.clamp(min, max)),
+        Overflow::Error => runtime_error(ErrorKind::Overflow, line, &format!("Estouro de inteiro em {}", operation())),
+    }
+}
+
+// Keeps the bits of the width, which is what the machine does
+pub fn wrap(exact: i128) -> Value {
+    let modulus = 1 << width();
+    let value = exact.rem_euclid(modulus);
+    int(if value > range().1 { value - modulus } else { value })
+}
+
+// Products of unsigned 64 bit integers may not fit, the ones that don't only need to
+// stay out of range, or keep their lower bits when wrapped
+pub fn mul(left: i128, right: i128) -> i128 {
+    match left.checked_mul(right) {
+        Some(product) => product,
+        None if overflow() == Overflow::Wrap => left.wrapping_mul(right),
+        None if (left < 0) != (right < 0) => i128::MIN,
+        None => i128::MAX,
+    }
+}
+
+// Wrapped powers keep the lower bits right, the others only need to stay out of range
+pub fn pow(base: i128, exponent: i128) -> i128 {
+    if overflow() != Overflow::Wrap {
+        // An exponent with the same parity gives the same sign, or the same result for -1, 0 and 1
+        let exponent = u32::try_from(exponent).unwrap_or(u32::MAX - 1 + (exponent % 2) as u32);
+        return base.saturating_pow(exponent);
+    }
+
+    let (mut result, mut base, mut exponent) = (1i128, base, exponent);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(base);
+        }
+        base = base.wrapping_mul(base);
+        exponent >>= 1;
+    }
+    result
+}
+
+// Integer results of big or rational operations, which only stay big with --bigint
+pub fn from_big(value: BigInt, line: u16, operation: impl Fn() -> String) -> Value {
+    if unbounded() {
+        return Value::from_big(value);
+    }
+
+    let exact = match value.to_i128() {
+        Some(exact) => exact,
+        // 2^64 is a multiple of 2^width, so the remainder keeps the bits that wrap
+        None if overflow() == Overflow::Wrap => {
+            let modulus = BigInt::from_i64(2).pow(64);
+            value.div_rem(&modulus).1.to_i128().expect("Bad remainder")
+        }
+        None if value.is_negative() => i128::MIN,
+        None => i128::MAX,
+    };
+
+    narrow(exact, line, operation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::errors;
+
+    const MODES: [Overflow; 3] = [Overflow::Wrap, Overflow::Saturate, Overflow::Error];
+
+    // Each test runs on its own thread, so the settings don't leak between them
+    fn configure(width: u32, unsigned: bool, overflow: Overflow) {
+        set_unbounded(false);
+        set_width(width);
+        set_unsigned(unsigned);
+        set_overflow(overflow);
+    }
+
+    // The integer the exact result becomes, or None when it overflows
+    fn fit(exact: i128) -> Option<String> {
+        match errors::catch(|| narrow(exact, 1, || exact.to_string())) {
+            Ok(value) => Some(value.to_string()),
+            Err(error) => {
+                assert!(matches!(error.kind, ErrorKind::Overflow));
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn values_in_range_are_kept_in_every_mode() {
+        for (width, unsigned) in [(8, false), (16, false), (32, false), (64, false), (8, true), (16, true), (32, true), (64, true)] {
+            for overflow in MODES {
+                configure(width, unsigned, overflow);
+                let (min, max) = range();
+                for exact in [min, min + 1, 0, max - 1, max] {
+                    assert_eq!(fit(exact), Some(exact.to_string()), "{} bits {}", width, unsigned);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_of_every_width() {
+        let expected = [
+            (8, false, -128, 127),
+            (16, false, -32768, 32767),
+            (32, false, -2147483648, 2147483647),
+            (64, false, i64::MIN as i128, i64::MAX as i128),
+            (8, true, 0, 255),
+            (16, true, 0, 65535),
+            (32, true, 0, 4294967295),
+            (64, true, 0, u64::MAX as i128),
+        ];
+        for (width, unsigned, min, max) in expected {
+            configure(width, unsigned, Overflow::Error);
+            assert_eq!(range(), (min, max));
+        }
+    }
+
+    #[test]
+    fn wrap_keeps_the_lower_bits() {
+        configure(8, false, Overflow::Wrap);
+        assert_eq!(fit(128), Some("-128".to_string()));
+        assert_eq!(fit(-129), Some("127".to_string()));
+        assert_eq!(fit(256 + 5), Some("5".to_string()));
+
+        configure(16, true, Overflow::Wrap);
+        assert_eq!(fit(65536), Some("0".to_string()));
+        assert_eq!(fit(-1), Some("65535".to_string()));
+
+        configure(32, false, Overflow::Wrap);
+        assert_eq!(fit(2147483648), Some("-2147483648".to_string()));
+
+        configure(64, true, Overflow::Wrap);
+        assert_eq!(fit(-1), Some(u64::MAX.to_string()));
+        assert_eq!(wrap(1 << 64).to_string(), "0");
+    }
+
+    #[test]
+    fn saturate_gives_the_closest_integer() {
+        configure(8, false, Overflow::Saturate);
+        assert_eq!(fit(1000), Some("127".to_string()));
+        assert_eq!(fit(-1000), Some("-128".to_string()));
+
+        configure(64, false, Overflow::Saturate);
+        assert_eq!(fit(i128::MAX), Some(i64::MAX.to_string()));
+
+        configure(32, true, Overflow::Saturate);
+        assert_eq!(fit(-1), Some("0".to_string()));
+        assert_eq!(fit(1 << 40), Some("4294967295".to_string()));
+    }
+
+    #[test]
+    fn error_mode_rejects_what_does_not_fit() {
+        for (width, unsigned) in [(8, false), (16, true), (32, false), (64, true)] {
+            configure(width, unsigned, Overflow::Error);
+            let (min, max) = range();
+            assert_eq!(fit(max + 1), None);
+            assert_eq!(fit(min - 1), None);
+        }
+    }
+
+    #[test]
+    fn parse_checks_the_width() {
+        configure(8, false, Overflow::Error);
+        assert_eq!(parse("127", 10).map(|value| value.to_string()), Some("127".to_string()));
+        assert_eq!(parse("-128", 10).map(|value| value.to_string()), Some("-128".to_string()));
+        assert!(parse("128", 10).is_none());
+        assert!(parse("12a", 10).is_none());
+
+        configure(16, true, Overflow::Error);
+        assert!(parse("-1", 10).is_none());
+        assert_eq!(parse("ffff", 16).map(|value| value.to_string()), Some("65535".to_string()));
+
+        configure(64, true, Overflow::Error);
+        assert_eq!(parse("18446744073709551615", 10).map(|value| value.to_string()), Some(u64::MAX.to_string()));
+        assert!(parse("18446744073709551616", 10).is_none());
+    }
+
+    #[test]
+    fn parse_bits_reads_twos_complement() {
+        configure(8, false, Overflow::Error);
+        assert_eq!(parse_bits("ff", 16).map(|value| value.to_string()), Some("-1".to_string()));
+        assert_eq!(parse_bits("10000000", 2).map(|value| value.to_string()), Some("-128".to_string()));
+        assert!(parse_bits("100", 16).is_none());
+
+        configure(8, true, Overflow::Error);
+        assert_eq!(parse_bits("ff", 16).map(|value| value.to_string()), Some("255".to_string()));
+
+        configure(32, false, Overflow::Error);
+        assert_eq!(parse_bits("ffffffff", 16).map(|value| value.to_string()), Some("-1".to_string()));
+        assert_eq!(parse_bits("17", 8).map(|value| value.to_string()), Some("15".to_string()));
+
+        configure(64, false, Overflow::Error);
+        assert_eq!(parse_bits("8000000000000000", 16).map(|value| value.to_string()), Some(i64::MIN.to_string()));
+    }
+
+    #[test]
+    fn unsigned_64_bit_products() {
+        let max = u64::MAX as i128;
+
+        configure(64, true, Overflow::Wrap);
+        assert_eq!(fit(mul(max, max)), Some("1".to_string()));
+        assert_eq!(fit(mul(max, 2)), Some((u64::MAX - 1).to_string()));
+
+        configure(64, true, Overflow::Saturate);
+        assert_eq!(fit(mul(max, max)), Some(u64::MAX.to_string()));
+
+        configure(64, true, Overflow::Error);
+        assert_eq!(fit(mul(max, max)), None);
+        assert_eq!(fit(mul(max, 1)), Some(u64::MAX.to_string()));
+
+        configure(64, false, Overflow::Saturate);
+        assert_eq!(fit(mul(i64::MIN as i128, i64::MIN as i128)), Some(i64::MAX.to_string()));
+    }
+
+    #[test]
+    fn powers_in_every_mode() {
+        configure(8, false, Overflow::Wrap);
+        assert_eq!(fit(pow(2, 7)), Some("-128".to_string()));
+        assert_eq!(fit(pow(3, 5)), Some("-13".to_string()));
+
+        configure(8, false, Overflow::Saturate);
+        assert_eq!(fit(pow(-2, 1001)), Some("-128".to_string()));
+        assert_eq!(fit(pow(-1, i128::MAX)), Some("-1".to_string()));
+
+        configure(8, false, Overflow::Error);
+        assert_eq!(fit(pow(2, 6)), Some("64".to_string()));
+        assert_eq!(fit(pow(2, 7)), None);
+    }
+
+    #[test]
+    fn big_results_outside_unbounded_mode() {
+        let huge = BigInt::parse("-100000000000000000000000000000000000001", 10).expect("Bad test integer");
+
+        configure(32, false, Overflow::Saturate);
+        assert_eq!(from_big(huge.clone(), 1, String::new).to_string(), "-2147483648");
+
+        configure(8, true, Overflow::Wrap);
+        assert_eq!(from_big(huge, 1, String::new).to_string(), "255");
+    }
+}
@@ -1,9 +1,132 @@
 use std::collections::HashMap;
+use std::rc::Rc;
+use crate::interpreter::commands::Function;
+use crate::interpreter::errors::runtime_error;
+use crate::interpreter::errors::ErrorKind;
+use crate::interpreter::values::Value;
+use crate::interpreter::values::Selector;
 
-pub fn write(map: &mut HashMap::<String,i32>,name: &String,data: i32){
-  map.insert(name.clone(),data);
+// Variables visible inside one block
+type Scope = HashMap::<String,Value>;
+
+// Every function call runs in its own frame, the first one holds the program variables.
+// A frame is a stack of scopes: its first scope holds the variables created by plain
+// assignments and the parameters, the others hold the variables declared with var
+// in the blocks being executed.
+pub struct Memory {
+  frames: Vec::<Vec::<Scope>>,
+  functions: HashMap::<String,Rc<Function>>,
+}
+
+impl Memory {
+  pub fn new(functions: HashMap::<String,Rc<Function>>) -> Memory {
+    Memory {frames: vec![vec![Scope::new()]],functions}
+  }
+}
+
+fn frame(mem: &Memory) -> &Vec::<Scope>{
+  mem.frames.last().expect("No frame")
+}
+
+fn frame_mut(mem: &mut Memory) -> &mut Vec::<Scope>{
+  mem.frames.last_mut().expect("No frame")
+}
+
+// Creates the variable in the innermost scope, shadowing any outer one
+pub fn declare(mem: &mut Memory,name: &str,data: Value){
+  frame_mut(mem).last_mut().expect("No scope").insert(name.to_string(),data);
+}
+
+// Updates the innermost variable with that name, creating it in the first scope when there is none
+pub fn write(mem: &mut Memory,name: &str,data: Value){
+  let frame = frame_mut(mem);
+
+  match frame.iter_mut().rev().find(|scope| scope.contains_key(name)) {
+    Some(scope) => scope.insert(name.to_string(),data),
+    None => frame[0].insert(name.to_string(),data),
+  };
 }
 
-pub fn read(map: &HashMap::<String,i32>,name: &String) -> i32{
-  map.get(name).expect("Unknow var").clone()
-}
\ No newline at end of file
+// Writes to a part of a variable, following array indexes and record fields
+pub fn write_at(mem: &mut Memory,name: &str,path: &[Selector],data: Value,line: u16){
+  let (last,path) = path.split_last().expect("Empty path");
+  let mut target = lookup_mut(mem,name,line);
+
+  for selector in path {
+    target = target.select_mut(selector,line);
+  }
+
+  target.set(last,data,line);
+}
+
+pub fn read(mem: &Memory,name: &str,line: u16) -> Value{
+  lookup(mem,name,line).clone()
+}
+
+pub fn read_at(mem: &Memory,name: &str,path: &[Selector],line: u16) -> Value{
+  let mut target = lookup(mem,name,line);
+
+  for selector in path {
+    target = target.select(selector,line);
+  }
+
+  target.clone()
+}
+
+pub fn find<'a>(mem: &'a Memory,name: &str) -> Option<&'a Value>{
+  frame(mem).iter().rev().find_map(|scope| scope.get(name))
+}
+
+pub fn lookup<'a>(mem: &'a Memory,name: &str,line: u16) -> &'a Value{
+  match find(mem,name) {
+    Some(value) => value,
+    None => undefined(name,line)
+  }
+}
+
+pub fn lookup_mut<'a>(mem: &'a mut Memory,name: &str,line: u16) -> &'a mut Value{
+  match frame_mut(mem).iter_mut().rev().find_map(|scope| scope.get_mut(name)) {
+    Some(value) => value,
+    None => undefined(name,line)
+  }
+}
+
+// Variables assigned only in branches that didn't run are still unknown
+fn undefined(name: &str,line: u16) -> ! {
+  runtime_error(ErrorKind::UndefinedVariable,line,&format!("Variável {} não definida",name))
+}
+
+pub fn function(mem: &Memory,name: &str) -> Rc<Function>{
+  mem.functions.get(name).expect("Unknow function").clone()
+}
+
+// Depth of the frames and scopes, to go back to after an error
+pub fn mark(mem: &Memory) -> (usize,usize){
+  (mem.frames.len(),frame(mem).len())
+}
+
+pub fn restore(mem: &mut Memory,(frames,scopes): (usize,usize)){
+  mem.frames.truncate(frames);
+  frame_mut(mem).truncate(scopes);
+}
+
+pub fn push_scope(mem: &mut Memory){
+  frame_mut(mem).push(Scope::new());
+}
+
+pub fn pop_scope(mem: &mut Memory){
+  frame_mut(mem).pop();
+}
+
+pub fn push_frame(mem: &mut Memory){
+  mem.frames.push(vec![Scope::new()]);
+}
+
+pub fn pop_frame(mem: &mut Memory){
+  mem.frames.pop();
+}
+
+// Number of function calls currently running
+pub fn depth(mem: &Memory) -> usize{
+  mem.frames.len() - 1
+}
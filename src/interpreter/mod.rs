@@ -1,3 +1,8 @@
+pub mod bigint;
 pub mod commands;
+pub mod errors;
 pub mod expressions;
+pub mod integers;
 pub mod memory;
+pub mod rational;
+pub mod values;
@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use crate::interpreter::bigint::BigInt;
+
+// An exact fraction, kept in lowest terms with a positive denominator
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: BigInt,
+    denominator: BigInt,
+}
+
+impl Rational {
+    // The denominator must not be zero
+    pub fn new(numerator: BigInt, denominator: BigInt) -> Rational {
+        assert!(!denominator.is_zero(), "Zero denominator");
+
+        let gcd = numerator.gcd(&denominator);
+        let (mut numerator, mut denominator) = (numerator.div_rem(&gcd).0, denominator.div_rem(&gcd).0);
+        if denominator.is_negative() {
+            numerator = -&numerator;
+            denominator = -&denominator;
+        }
+
+        Rational { numerator, denominator }
+    }
+
+    pub fn from_integer(value: BigInt) -> Rational {
+        Rational { numerator: value, denominator: BigInt::from_i64(1) }
+    }
+
+    // Integers are the rationals whose denominator is 1
+    pub fn to_integer(&self) -> Option<&BigInt> {
+        match self.denominator.to_i64() {
+            Some(1) => Some(&self.numerator),
+            _ => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator.is_zero()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.numerator.to_f64() / self.denominator.to_f64()
+    }
+
+    // Rounds towards zero
+    pub fn truncate(&self) -> BigInt {
+        self.numerator.div_rem(&self.denominator).0
+    }
+
+    // A negative exponent gives the power of the inverse, which must not be zero
+    pub fn pow(&self, exponent: i32) -> Rational {
+        let power = Rational {
+            numerator: self.numerator.pow(exponent.unsigned_abs()),
+            denominator: self.denominator.pow(exponent.unsigned_abs()),
+        };
+
+        if exponent < 0 {
+            Rational::new(power.denominator, power.numerator)
+        } else {
+            power
+        }
+    }
+}
+
+impl Add for &Rational {
+    type Output = Rational;
+
+    fn add(self, other: &Rational) -> Rational {
+        Rational::new(
+            &(&self.numerator * &other.denominator) + &(&other.numerator * &self.denominator),
+            &self.denominator * &other.denominator,
+        )
+    }
+}
+
+impl Sub for &Rational {
+    type Output = Rational;
+
+    fn sub(self, other: &Rational) -> Rational {
+        self + &-other
+    }
+}
+
+impl Mul for &Rational {
+    type Output = Rational;
+
+    fn mul(self, other: &Rational) -> Rational {
+        Rational::new(&self.numerator * &other.numerator, &self.denominator * &other.denominator)
+    }
+}
+
+// The divisor must not be zero
+impl Div for &Rational {
+    type Output = Rational;
+
+    fn div(self, other: &Rational) -> Rational {
+        Rational::new(&self.numerator * &other.denominator, &self.denominator * &other.numerator)
+    }
+}
+
+// What is left after taking out the quotient truncated towards zero
+impl Rem for &Rational {
+    type Output = Rational;
+
+    fn rem(self, other: &Rational) -> Rational {
+        self - &(other * &Rational::from_integer((self / other).truncate()))
+    }
+}
+
+impl Neg for &Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational { numerator: -&self.numerator, denominator: self.denominator.clone() }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (&self.numerator * &other.denominator).cmp(&(&other.numerator * &self.denominator))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numerator: i64, denominator: i64) -> Rational {
+        Rational::new(BigInt::from_i64(numerator), BigInt::from_i64(denominator))
+    }
+
+    #[test]
+    fn new_reduces_to_lowest_terms() {
+        assert_eq!(ratio(6, 8).to_string(), "3/4");
+        assert_eq!(ratio(-6, 8).to_string(), "-3/4");
+        assert_eq!(ratio(0, 5).to_string(), "0/1");
+        assert_eq!(ratio(10, 5).to_integer().map(|value| value.to_string()), Some("2".to_string()));
+        assert!(ratio(1, 3).to_integer().is_none());
+    }
+
+    #[test]
+    fn new_moves_the_sign_to_the_numerator() {
+        assert_eq!(ratio(3, -4).to_string(), "-3/4");
+        assert_eq!(ratio(-3, -4).to_string(), "3/4");
+        assert_eq!(ratio(0, -7).to_string(), "0/1");
+        assert!(ratio(1, -2) == ratio(-1, 2));
+    }
+
+    #[test]
+    fn arithmetic_stays_reduced() {
+        assert_eq!((&ratio(1, 6) + &ratio(1, 3)).to_string(), "1/2");
+        assert_eq!((&ratio(1, 2) - &ratio(3, 4)).to_string(), "-1/4");
+        assert_eq!((&ratio(2, 3) * &ratio(9, 4)).to_string(), "3/2");
+        assert_eq!((&ratio(2, 3) / &ratio(-4, 9)).to_string(), "-3/2");
+        assert_eq!(ratio(-2, 3).pow(-2).to_string(), "9/4");
+    }
+
+    #[test]
+    fn rem_has_the_sign_of_the_dividend() {
+        assert_eq!((&ratio(7, 2) % &ratio(1, 1)).to_string(), "1/2");
+        assert_eq!((&ratio(-7, 2) % &ratio(1, 1)).to_string(), "-1/2");
+        assert_eq!((&ratio(7, 2) % &ratio(-1, 1)).to_string(), "1/2");
+        assert_eq!((&ratio(5, 3) % &ratio(1, 2)).to_string(), "1/6");
+        assert_eq!((&ratio(3, 2) % &ratio(3, 4)).to_string(), "0/1");
+    }
+
+    #[test]
+    fn truncate_and_order() {
+        assert_eq!(ratio(-7, 2).truncate().to_string(), "-3");
+        assert_eq!(ratio(7, 2).truncate().to_string(), "3");
+        assert!(ratio(-1, 2) < ratio(1, 3));
+        assert!(ratio(2, 3) > ratio(3, 5));
+    }
+}
@@ -0,0 +1,402 @@
+use crate::interpreter::bigint::BigInt;
+use crate::interpreter::errors::runtime_error;
+use crate::interpreter::rational::Rational;
+use crate::interpreter::errors::ErrorKind;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    // An integer that does not fit in an Int, only made with --bigint
+    Big(BigInt),
+    // A fraction made by // or rational, never with denominator 1
+    Rational(Rational),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Array(Vec<Value>),
+    Record(Record),
+    Map(Box<Map>),
+    Enum(Variant),
+}
+
+// A variant of an enum, the ordinal is its position in the declaration
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Variant {
+    pub ty: String,
+    pub name: String,
+    pub ordinal: i64,
+}
+
+// Fields are kept in the order of the record declaration
+#[derive(Clone, PartialEq)]
+pub struct Record {
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+// Entries are kept in insertion order, which is the order keys and for ... in follow,
+// and found through the index of their keys
+#[derive(Clone, Default)]
+pub struct Map {
+    entries: Vec<(Value, Value)>,
+    index: HashMap<Key, usize>,
+}
+
+// The values that can be map keys, which unlike floats can be hashed
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Int(i64),
+    Big(BigInt),
+    Rational(Rational),
+    Str(String),
+    Bool(bool),
+    Enum(Variant),
+}
+
+impl Key {
+    fn new(value: &Value) -> Option<Key> {
+        match value {
+            Value::Int(value) => Some(Key::Int(*value)),
+            Value::Big(value) => Some(Key::Big(value.clone())),
+            Value::Rational(value) => Some(Key::Rational(value.clone())),
+            Value::Str(value) => Some(Key::Str(value.clone())),
+            Value::Bool(value) => Some(Key::Bool(*value)),
+            Value::Enum(value) => Some(Key::Enum(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Map {
+    // Floats don't compare reliably and can't be keys, neither can arrays, records and maps
+    pub fn check_key(key: &Value, line: u16) {
+        if Key::new(key).is_none() {
+            runtime_error(ErrorKind::Type, line, &format!("Não é possível usar {} como chave de mapa", key.kind()));
+        }
+    }
+
+    fn position(&self, key: &Value) -> Option<usize> {
+        Key::new(key).and_then(|key| self.index.get(&key).copied())
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.position(key).map(|position| &self.entries[position].1)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        self.position(key).map(|position| &mut self.entries[position].1)
+    }
+
+    // A new key goes after the existing ones, an existing key keeps its place
+    pub fn insert(&mut self, key: Value, value: Value) {
+        match self.get_mut(&key) {
+            Some(current) => *current = value,
+            None => {
+                self.index.insert(Key::new(&key).expect("Bad key"), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    // The entries after the removed one move back a place
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        let position = self.index.remove(&Key::new(key)?)?;
+        for index in self.index.values_mut() {
+            if *index > position {
+                *index -= 1;
+            }
+        }
+
+        Some(self.entries.remove(position).1)
+    }
+
+    pub fn keys(&self) -> Vec<Value> {
+        self.entries.iter().map(|(key, _)| key.clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// Two maps are equal when they have the same entries, in any order
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.len() == other.len()
+            && self.entries.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+// One step of the path to the part of a variable being read or assigned
+pub enum Selector {
+    Index(Value),
+    Field(String),
+}
+
+impl Value {
+    // Integers that fit in 64 bits are always kept as Int, so each one has a single form
+    pub fn from_big(value: BigInt) -> Value {
+        match value.to_i64() {
+            Some(value) => Value::Int(value),
+            None => Value::Big(value),
+        }
+    }
+
+    pub fn as_int(&self, line: u16) -> i64 {
+        match self {
+            Value::Int(value) => *value,
+            Value::Big(value) => runtime_error(
+                ErrorKind::Value,
+                line,
+                &format!("Inteiro {} grande demais aqui, o maior permitido é {}", value, i64::MAX),
+            ),
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um inteiro em vez de {}", self.kind())),
+        }
+    }
+
+    // Unsigned 64 bit integers are the only Big ones without --bigint
+    pub fn as_wide(&self, line: u16) -> i128 {
+        match self {
+            Value::Int(value) => *value as i128,
+            Value::Big(value) => match value.to_i128() {
+                Some(value) => value,
+                None => runtime_error(ErrorKind::Value, line, &format!("Inteiro {} grande demais aqui", value)),
+            },
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um inteiro em vez de {}", self.kind())),
+        }
+    }
+
+    pub fn as_big(&self, line: u16) -> BigInt {
+        match self {
+            Value::Int(value) => BigInt::from_i64(*value),
+            Value::Big(value) => value.clone(),
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um inteiro em vez de {}", self.kind())),
+        }
+    }
+
+    // Integers are promoted to rationals
+    pub fn as_rational(&self, line: u16) -> Rational {
+        match self {
+            Value::Rational(value) => value.clone(),
+            Value::Int(_) | Value::Big(_) => Rational::from_integer(self.as_big(line)),
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um racional em vez de {}", self.kind())),
+        }
+    }
+
+    // Integers and rationals are promoted to floats
+    pub fn as_float(&self, line: u16) -> f64 {
+        match self {
+            Value::Int(value) => *value as f64,
+            Value::Big(value) => value.to_f64(),
+            Value::Rational(value) => value.to_f64(),
+            Value::Float(value) => *value,
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um número em vez de {}", self.kind())),
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Int(_) | Value::Big(_) | Value::Rational(_) | Value::Float(_))
+    }
+
+    pub fn as_str(&self, line: u16) -> &str {
+        match self {
+            Value::Str(text) => text,
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperada uma string em vez de {}", self.kind())),
+        }
+    }
+
+    pub fn as_bool(&self, line: u16) -> bool {
+        match self {
+            Value::Bool(value) => *value,
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um booleano em vez de {}", self.kind())),
+        }
+    }
+
+    pub fn as_map(&self, line: u16) -> &Map {
+        match self {
+            Value::Map(map) => map,
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um mapa em vez de {}", self.kind())),
+        }
+    }
+
+    pub fn as_map_mut(&mut self, line: u16) -> &mut Map {
+        match self {
+            Value::Map(map) => map,
+            _ => runtime_error(ErrorKind::Type, line, &format!("Esperado um mapa em vez de {}", self.kind())),
+        }
+    }
+
+    // Name of the value type used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Value::Int(_) | Value::Big(_) => "um inteiro",
+            Value::Rational(_) => "um racional",
+            Value::Float(_) => "um float",
+            Value::Bool(_) => "um booleano",
+            Value::Str(_) => "uma string",
+            Value::Array(_) => "um vetor",
+            Value::Record(_) => "um registro",
+            Value::Map(_) => "um mapa",
+            Value::Enum(_) => "uma variante de enum",
+        }
+    }
+
+    // Checks the bounds of an index used on this value
+    pub fn position(&self, index: i128, line: u16) -> usize {
+        match self {
+            Value::Array(items) => {
+                if index < 0 || index as usize >= items.len() {
+                    runtime_error(
+                        ErrorKind::Index,
+                        line,
+                        &format!("Índice {} fora dos limites de um vetor de tamanho {}", index, items.len()),
+                    );
+                }
+                index as usize
+            }
+            _ => runtime_error(ErrorKind::Type, line, &format!("Não é possível indexar {}", self.kind())),
+        }
+    }
+
+    pub fn element(&self, index: i128, line: u16) -> &Value {
+        let position = self.position(index, line);
+
+        match self {
+            Value::Array(items) => &items[position],
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn element_mut(&mut self, index: i128, line: u16) -> &mut Value {
+        let position = self.position(index, line);
+
+        match self {
+            Value::Array(items) => &mut items[position],
+            _ => unreachable!(),
+        }
+    }
+
+    // Checks that this value is a record with that field
+    fn field_position(&self, name: &str, line: u16) -> usize {
+        match self {
+            Value::Record(record) => match record.fields.iter().position(|(field, _)| field == name) {
+                Some(position) => position,
+                None => runtime_error(ErrorKind::Type, line, &format!("Registro {} não tem o campo {}", record.name, name)),
+            },
+            _ => runtime_error(ErrorKind::Type, line, &format!("Não é possível ler o campo {} de {}", name, self.kind())),
+        }
+    }
+
+    pub fn field(&self, name: &str, line: u16) -> &Value {
+        let position = self.field_position(name, line);
+
+        match self {
+            Value::Record(record) => &record.fields[position].1,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn field_mut(&mut self, name: &str, line: u16) -> &mut Value {
+        let position = self.field_position(name, line);
+
+        match self {
+            Value::Record(record) => &mut record.fields[position].1,
+            _ => unreachable!(),
+        }
+    }
+
+    // Value of a map entry, the key must be there
+    pub fn entry(&self, key: &Value, line: u16) -> &Value {
+        match self {
+            Value::Map(map) => match map.get(key) {
+                Some(value) => value,
+                None => runtime_error(ErrorKind::Key, line, &format!("Chave {} não encontrada no mapa", key)),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn entry_mut(&mut self, key: &Value, line: u16) -> &mut Value {
+        match self {
+            Value::Map(map) => match map.get_mut(key) {
+                Some(value) => value,
+                None => runtime_error(ErrorKind::Key, line, &format!("Chave {} não encontrada no mapa", key)),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn select(&self, selector: &Selector, line: u16) -> &Value {
+        match (selector, self) {
+            (Selector::Index(key), Value::Map(_)) => self.entry(key, line),
+            (Selector::Index(index), _) => self.element(index.as_wide(line), line),
+            (Selector::Field(name), _) => self.field(name, line),
+        }
+    }
+
+    pub fn select_mut(&mut self, selector: &Selector, line: u16) -> &mut Value {
+        match (selector, &self) {
+            (Selector::Index(key), Value::Map(_)) => self.entry_mut(key, line),
+            (Selector::Index(index), _) => self.element_mut(index.as_wide(line), line),
+            (Selector::Field(name), _) => self.field_mut(name, line),
+        }
+    }
+
+    // Replaces the selected part of the value, assigning to a missing map key adds it
+    pub fn set(&mut self, selector: &Selector, data: Value, line: u16) {
+        match (selector, self) {
+            (Selector::Index(key), Value::Map(map)) => {
+                Map::check_key(key, line);
+                map.insert(key.clone(), data);
+            }
+            (selector, value) => *value.select_mut(selector, line) = data,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Big(value) => write!(f, "{}", value),
+            Value::Rational(value) => write!(f, "{}", value),
+            // Debug keeps the decimal point on whole floats
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Str(text) => write!(f, "{}", text),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Record(record) => {
+                write!(f, "{}(", record.name)?;
+                for (i, (name, value)) in record.fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                write!(f, ")")
+            }
+            Value::Enum(variant) => write!(f, "{}", variant.name),
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
@@ -1,24 +1,26 @@
 use crate::lexical::Lexeme;
+use crate::lexical::sources;
 use crate::lexical::tokens::Token;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-pub fn lexical_analysis(f: File) -> Vec<Lexeme> {
+pub fn lexical_analysis(f: File, name: &str) -> Vec<Lexeme> {
     let mut reader = BufReader::new(f);
     let mut line = String::new();
     let mut lexes = Vec::<Lexeme>::new();
-    let mut line_number: u16 = 1;
+    let mut line_number: u16 = sources::add(name);
     let mut next_c: char = ' ';
 
     loop {
         let lex = next_token(&mut reader, &mut line, &mut next_c, &mut line_number);
-        //println!("({},{})",lex.token, Token::to_string(&lex.ttype).expect("Failed to parse"));
+        //println!("({},{})",lex.token, Token::to_string(lex.ttype).expect("Failed to parse"));
 
-        if matches!(lex.ttype, Token::InvalidToken) {
-            println!("Token inválido na linha {}", line_number);
+        if matches!(lex.ttype, Token::Invalid) {
+            println!("Token inválido em {}", sources::location(line_number));
         } else if matches!(lex.ttype, Token::UnexpectedEof) {
-            println!("Fim de arquivo inesperado na linha {}", line_number);
+            println!("Fim de arquivo inesperado em {}", sources::location(line_number));
         } else if matches!(lex.ttype, Token::EndOfFile) {
+            sources::close(line_number);
             lexes.push(lex);
             return lexes;
         }
@@ -56,7 +58,7 @@ fn next_token(
                 c = match line.pop() {
                     Some(x) => x,
                     None => {
-                        if state == 4 {
+                        if state == 4 || state == 9 || state == 10 || state == 11 || state == 13 || state == 14 {
                             lex.ttype = Token::UnexpectedEof;
                         } else {
                             lex.ttype = Token::EndOfFile;
@@ -80,13 +82,9 @@ fn next_token(
                 } else if c == '\n' {
                     *line_number += 1;
                     state = 1;
-                } else if c == '=' || c == '<' || c == '>' {
-                    lex.token.push_str(&c.to_string());
-                    state = 3;
-                } else if c == '!' {
-                    lex.token.push_str(&c.to_string());
-                    state = 4;
-                } else if c == ';'
+                } else if c == '='
+                    || c == '<'
+                    || c == '>'
                     || c == '+'
                     || c == '-'
                     || c == '*'
@@ -94,19 +92,42 @@ fn next_token(
                     || c == '%'
                     || c == '^'
                 {
-                    lex.token.push_str(&c.to_string());
+                    lex.token.push(c);
+                    state = 3;
+                } else if c == '!' {
+                    lex.token.push(c);
+                    state = 4;
+                } else if c == ';'
+                    || c == '('
+                    || c == ')'
+                    || c == ','
+                    || c == '['
+                    || c == ']'
+                    || c == '{'
+                    || c == '}'
+                    || c == ':'
+                    || c == '&'
+                    || c == '|'
+                    || c == '~'
+                {
+                    lex.token.push(c);
                     state = 7;
                 }
                 // var names
                 else if c == '_' || c.is_alphabetic() {
-                    lex.token.push_str(&c.to_string());
+                    lex.token.push(c);
                     state = 5;
-                } else if c.is_digit(10) {
-                    lex.token.push_str(&c.to_string());
+                } else if c.is_ascii_digit() {
+                    lex.token.push(c);
                     state = 6;
+                } else if c == '"' {
+                    state = 9;
+                } else if c == '.' {
+                    lex.token.push(c);
+                    state = 16;
                 } else {
-                    lex.token.push_str(&c.to_string());
-                    lex.ttype = Token::InvalidToken;
+                    lex.token.push(c);
+                    lex.ttype = Token::Invalid;
                     state = 8;
                 }
             }
@@ -120,9 +141,15 @@ fn next_token(
                 }
             }
 
+            // operators that may be followed by =, and the ++, --, //, << and >> operators
             3 => {
-                if c == '=' {
-                    lex.token.push_str(&c.to_string());
+                let doubled = (c == '+' || c == '-' || c == '/' || c == '<' || c == '>') && lex.token.starts_with(c);
+
+                if c == '>' && doubled {
+                    lex.token.push(c);
+                    state = 17;
+                } else if c == '=' || doubled {
+                    lex.token.push(c);
                     state = 7;
                 } else {
                     *next_c = c;
@@ -132,17 +159,17 @@ fn next_token(
 
             4 => {
                 if c == '=' {
-                    lex.token.push_str(&c.to_string());
+                    lex.token.push(c);
                     state = 7;
                 } else {
-                    lex.ttype = Token::InvalidToken;
+                    lex.ttype = Token::Invalid;
                     state = 8;
                 }
             }
 
             5 => {
                 if c == '_' || c.is_alphanumeric() {
-                    lex.token.push_str(&c.to_string());
+                    lex.token.push(c);
                     state = 5;
                 } else {
                     *next_c = c;
@@ -151,10 +178,22 @@ fn next_token(
             }
 
             6 => {
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     // Append the digit
-                    lex.token.push_str(&c.to_string());
+                    lex.token.push(c);
+                    state = 6;
+                } else if c == '_' {
+                    // digit separator, left out of the token
                     state = 6;
+                } else if lex.token == "0" && matches!(c, 'x' | 'X' | 'b' | 'B' | 'o' | 'O') {
+                    lex.token.push(c.to_ascii_lowercase());
+                    state = 18;
+                } else if c == '.' {
+                    lex.token.push(c);
+                    state = 11;
+                } else if c == 'e' || c == 'E' {
+                    lex.token.push(c);
+                    state = 13;
                 } else {
                     // ignore the character
                     *next_c = c;
@@ -163,6 +202,142 @@ fn next_token(
                 }
             }
 
+            // decimal part, at least one digit after the point
+            11 => {
+                if c.is_ascii_digit() {
+                    lex.token.push(c);
+                    state = 12;
+                } else if c == '.' {
+                    // a range like 1..9, both dots go back to be read as ..
+                    lex.token.pop();
+                    line.insert(0, c);
+                    *next_c = c;
+                    lex.ttype = Token::Number;
+                    state = 8;
+                } else {
+                    lex.ttype = Token::Invalid;
+                    state = 8;
+                }
+            }
+
+            12 => {
+                if c.is_ascii_digit() {
+                    lex.token.push(c);
+                    state = 12;
+                } else if c == 'e' || c == 'E' {
+                    lex.token.push(c);
+                    state = 13;
+                } else {
+                    *next_c = c;
+                    lex.ttype = Token::Number;
+                    state = 8;
+                }
+            }
+
+            // exponent, with an optional sign
+            13 => {
+                if c == '+' || c == '-' {
+                    lex.token.push(c);
+                    state = 14;
+                } else if c.is_ascii_digit() {
+                    lex.token.push(c);
+                    state = 15;
+                } else {
+                    lex.ttype = Token::Invalid;
+                    state = 8;
+                }
+            }
+
+            14 => {
+                if c.is_ascii_digit() {
+                    lex.token.push(c);
+                    state = 15;
+                } else {
+                    lex.ttype = Token::Invalid;
+                    state = 8;
+                }
+            }
+
+            15 => {
+                if c.is_ascii_digit() {
+                    lex.token.push(c);
+                    state = 15;
+                } else {
+                    *next_c = c;
+                    lex.ttype = Token::Number;
+                    state = 8;
+                }
+            }
+
+            // string literals, the quotes are not part of the token
+            9 => {
+                if c == '"' {
+                    lex.ttype = Token::Str;
+                    state = 8;
+                } else if c == '\\' {
+                    state = 10;
+                } else if c == '\n' {
+                    *next_c = c;
+                    lex.ttype = Token::Invalid;
+                    state = 8;
+                } else {
+                    lex.token.push(c);
+                    state = 9;
+                }
+            }
+
+            10 => {
+                match c {
+                    'n' => lex.token.push('\n'),
+                    't' => lex.token.push('\t'),
+                    '"' => lex.token.push('"'),
+                    '\\' => lex.token.push('\\'),
+                    _ => {
+                        lex.ttype = Token::Invalid;
+                        state = 8;
+                        continue;
+                    }
+                }
+                state = 9;
+            }
+
+            // the >>> of logical shifts
+            17 => {
+                if c == '>' {
+                    lex.token.push(c);
+                } else {
+                    *next_c = c;
+                }
+                state = 7;
+            }
+
+            // hexadecimal, binary and octal integers, the parser checks the digits
+            18 => {
+                if c.is_ascii_hexdigit() {
+                    lex.token.push(c);
+                    state = 18;
+                } else if c == '_' {
+                    state = 18;
+                } else if lex.token.len() == 2 {
+                    lex.ttype = Token::Invalid;
+                    state = 8;
+                } else {
+                    *next_c = c;
+                    lex.ttype = Token::Number;
+                    state = 8;
+                }
+            }
+
+            // a single . or the .. of ranges
+            16 => {
+                if c == '.' {
+                    lex.token.push(c);
+                } else {
+                    *next_c = c;
+                }
+                state = 7;
+            }
+
             _ => unreachable!(),
         }
     }
@@ -172,5 +347,5 @@ fn next_token(
     }
 
     lex.line_number = *line_number;
-    return lex;
+    lex
 }
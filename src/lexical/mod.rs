@@ -1,4 +1,5 @@
 pub mod lexical_analizer;
+pub mod sources;
 pub mod tokens;
 use crate::lexical::tokens::Token;
 
@@ -0,0 +1,50 @@
+use std::cell::RefCell;
+
+// A program may be split in several files. Their lines are numbered one after
+// the other, so a line number alone tells which file it belongs to.
+struct Source {
+  name: String,
+  first: u16,
+  last: u16,
+}
+
+thread_local! {
+  static SOURCES: RefCell<Vec::<Source>> = const { RefCell::new(Vec::new()) };
+}
+
+// Registers a file about to be read, returning the number given to its first line
+pub fn add(name: &str) -> u16 {
+  SOURCES.with(|sources| {
+    let mut sources = sources.borrow_mut();
+    let first = sources.last().map_or(1,|source| source.last + 1);
+
+    sources.push(Source {name: name.to_string(),first,last: first});
+    first
+  })
+}
+
+// Records the last line of the file being read
+pub fn close(last: u16) {
+  SOURCES.with(|sources| {
+    if let Some(source) = sources.borrow_mut().last_mut() {
+      source.last = last;
+    }
+  })
+}
+
+// The file a line belongs to and its number inside that file
+pub fn split(line: u16) -> (String,u16) {
+  SOURCES.with(|sources| {
+    match sources.borrow().iter().rev().find(|source| source.first <= line) {
+      Some(source) => (source.name.clone(),line - source.first + 1),
+      None => (String::new(),line)
+    }
+  })
+}
+
+// Where a line is, as file:line
+pub fn location(line: u16) -> String {
+  let (name,line) = split(line);
+
+  format!("{}:{}",name,line)
+}
@@ -1,12 +1,23 @@
+#[derive(Copy, Clone, PartialEq)]
 pub enum Token {
     // Specials
     UnexpectedEof = -2,
-    InvalidToken = -1,
+    Invalid = -1,
     EndOfFile = 0,
 
     // Symbols
     Semicolon, // ;
     Assign,    // =
+    LParen,    // (
+    RParen,    // )
+    Comma,     // ,
+    LBracket,  // [
+    RBracket,  // ]
+    LBrace,    // {
+    RBrace,    // }
+    Colon,     // :
+    Range,     // ..
+    Dot,       // .
 
     // Logic operators
     Equal,        // ==
@@ -21,9 +32,28 @@ pub enum Token {
     Sub, // -
     Mul, // *
     Div, // /
+    RatDiv, // //
     Mod, // %
     Pow, // ^
 
+    // Bitwise operators
+    BitAnd,            // &
+    BitOr,             // |
+    BitNot,            // ~
+    ShiftLeft,         // <<
+    ShiftRight,        // >>
+    LogicalShiftRight, // >>>
+
+    // Assignment operators
+    AddAssign, // +=
+    SubAssign, // -=
+    MulAssign, // *=
+    DivAssign, // /=
+    ModAssign, // %=
+    PowAssign, // ^=
+    Increment, // ++
+    Decrement, // --
+
     // Keywords
     Program, // program
     While,   // while
@@ -37,20 +67,60 @@ pub enum Token {
     False,   // false
     Read,    // read
     Not,     // not
+    And,     // and
+    Or,      // or
+    Xor,     // xor
+    Func,    // func
+    Return,  // return
+    Declare, // var
+    Const,   // const
+    Import,  // import
+    Record,  // record
+    Enum,    // enum
+    Try,     // try
+    Catch,   // catch
+    Finally, // finally
+    Raise,   // raise
+    Assert,  // assert
+    Invariant, // invariant
+    Requires, // requires
+    Ensures, // ensures
+    For,     // for
+    To,      // to
+    Step,    // step
+    In,      // in
+    Repeat,  // repeat
+    Until,   // until
+    Break,   // break
+    Continue, // continue
+    Elif,    // elif
+    Case,    // case
+    Of,      // of
 
     // Others
     Number, // number
+    Str,    // "string"
     Var,    // variable
 }
 
 impl Token {
-    pub fn to_string(&self) -> Result<&str, &str> {
+    pub fn to_string(self) -> Result<&'static str, &'static str> {
         match self {
             Token::UnexpectedEof => Ok("UNEXPECTED_EOF"),
-            Token::InvalidToken => Ok("INVALID_TOKEN"),
+            Token::Invalid => Ok("INVALID_TOKEN"),
             Token::EndOfFile => Ok("EOF"),
             Token::Semicolon => Ok("SEMICOLON"),
             Token::Assign => Ok("ASSIGN"),
+            Token::LParen => Ok("LPAREN"),
+            Token::RParen => Ok("RPAREN"),
+            Token::Comma => Ok("COMMA"),
+            Token::LBracket => Ok("LBRACKET"),
+            Token::RBracket => Ok("RBRACKET"),
+            Token::LBrace => Ok("LBRACE"),
+            Token::RBrace => Ok("RBRACE"),
+            Token::Colon => Ok("COLON"),
+            Token::Range => Ok("RANGE"),
+            Token::Dot => Ok("DOT"),
             Token::Equal => Ok("EQUAL"),
             Token::NotEqual => Ok("NOT_EQUAL"),
             Token::Lower => Ok("LOWER"),
@@ -61,8 +131,23 @@ impl Token {
             Token::Sub => Ok("SUB"),
             Token::Mul => Ok("MUL"),
             Token::Div => Ok("DIV"),
+            Token::RatDiv => Ok("RAT_DIV"),
             Token::Mod => Ok("MOD"),
             Token::Pow => Ok("POW"),
+            Token::BitAnd => Ok("BIT_AND"),
+            Token::BitOr => Ok("BIT_OR"),
+            Token::BitNot => Ok("BIT_NOT"),
+            Token::ShiftLeft => Ok("SHIFT_LEFT"),
+            Token::ShiftRight => Ok("SHIFT_RIGHT"),
+            Token::LogicalShiftRight => Ok("LOGICAL_SHIFT_RIGHT"),
+            Token::AddAssign => Ok("ADD_ASSIGN"),
+            Token::SubAssign => Ok("SUB_ASSIGN"),
+            Token::MulAssign => Ok("MUL_ASSIGN"),
+            Token::DivAssign => Ok("DIV_ASSIGN"),
+            Token::ModAssign => Ok("MOD_ASSIGN"),
+            Token::PowAssign => Ok("POW_ASSIGN"),
+            Token::Increment => Ok("INCREMENT"),
+            Token::Decrement => Ok("DECREMENT"),
             Token::Program => Ok("PROGRAM"),
             Token::While => Ok("WHILE"),
             Token::Do => Ok("DO"),
@@ -75,7 +160,37 @@ impl Token {
             Token::False => Ok("FALSE"),
             Token::Read => Ok("READ"),
             Token::Not => Ok("NOT"),
+            Token::And => Ok("AND"),
+            Token::Or => Ok("OR"),
+            Token::Xor => Ok("XOR"),
+            Token::Func => Ok("FUNC"),
+            Token::Return => Ok("RETURN"),
+            Token::Declare => Ok("DECLARE"),
+            Token::Const => Ok("CONST"),
+            Token::Import => Ok("IMPORT"),
+            Token::Record => Ok("RECORD"),
+            Token::Enum => Ok("ENUM"),
+            Token::Try => Ok("TRY"),
+            Token::Catch => Ok("CATCH"),
+            Token::Finally => Ok("FINALLY"),
+            Token::Raise => Ok("RAISE"),
+            Token::Assert => Ok("ASSERT"),
+            Token::Invariant => Ok("INVARIANT"),
+            Token::Requires => Ok("REQUIRES"),
+            Token::Ensures => Ok("ENSURES"),
+            Token::For => Ok("FOR"),
+            Token::To => Ok("TO"),
+            Token::Step => Ok("STEP"),
+            Token::In => Ok("IN"),
+            Token::Repeat => Ok("REPEAT"),
+            Token::Until => Ok("UNTIL"),
+            Token::Break => Ok("BREAK"),
+            Token::Continue => Ok("CONTINUE"),
+            Token::Elif => Ok("ELIF"),
+            Token::Case => Ok("CASE"),
+            Token::Of => Ok("OF"),
             Token::Number => Ok("NUMBER"),
+            Token::Str => Ok("STRING"),
             Token::Var => Ok("VAR"),
         }
     }
@@ -84,6 +199,16 @@ impl Token {
         match s {
             ";" => Token::Semicolon,
             "=" => Token::Assign,
+            "(" => Token::LParen,
+            ")" => Token::RParen,
+            "," => Token::Comma,
+            "[" => Token::LBracket,
+            "]" => Token::RBracket,
+            "{" => Token::LBrace,
+            "}" => Token::RBrace,
+            ":" => Token::Colon,
+            ".." => Token::Range,
+            "." => Token::Dot,
             "==" => Token::Equal,
             "!=" => Token::NotEqual,
             "<" => Token::Lower,
@@ -93,9 +218,24 @@ impl Token {
             "+" => Token::Add,
             "-" => Token::Sub,
             "*" => Token::Mul,
-            "/=" => Token::Div,
+            "/" => Token::Div,
+            "//" => Token::RatDiv,
             "%" => Token::Mod,
             "^" => Token::Pow,
+            "&" => Token::BitAnd,
+            "|" => Token::BitOr,
+            "~" => Token::BitNot,
+            "<<" => Token::ShiftLeft,
+            ">>" => Token::ShiftRight,
+            ">>>" => Token::LogicalShiftRight,
+            "+=" => Token::AddAssign,
+            "-=" => Token::SubAssign,
+            "*=" => Token::MulAssign,
+            "/=" => Token::DivAssign,
+            "%=" => Token::ModAssign,
+            "^=" => Token::PowAssign,
+            "++" => Token::Increment,
+            "--" => Token::Decrement,
             "program" => Token::Program,
             "while" => Token::While,
             "do" => Token::Do,
@@ -108,6 +248,35 @@ impl Token {
             "false" => Token::False,
             "read" => Token::Read,
             "not" => Token::Not,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "xor" => Token::Xor,
+            "func" => Token::Func,
+            "return" => Token::Return,
+            "var" => Token::Declare,
+            "const" => Token::Const,
+            "import" => Token::Import,
+            "record" => Token::Record,
+            "enum" => Token::Enum,
+            "try" => Token::Try,
+            "catch" => Token::Catch,
+            "finally" => Token::Finally,
+            "raise" => Token::Raise,
+            "assert" => Token::Assert,
+            "invariant" => Token::Invariant,
+            "requires" => Token::Requires,
+            "ensures" => Token::Ensures,
+            "for" => Token::For,
+            "to" => Token::To,
+            "step" => Token::Step,
+            "in" => Token::In,
+            "repeat" => Token::Repeat,
+            "until" => Token::Until,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "elif" => Token::Elif,
+            "case" => Token::Case,
+            "of" => Token::Of,
             _ => Token::Var,
         }
     }
@@ -1,29 +1,106 @@
 use std::env;
 use std::io::ErrorKind;
 use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
 
 mod lexical;
 mod syntatic;
 mod interpreter;
 
+// Every call and nested command of the program recurses in the interpreter, so it
+// runs on a thread with room for MAX_CALL_DEPTH calls of deeply nested functions
+const STACK_SIZE: usize = 1 << 30;
+
 fn main() {
-  let args: Vec<String> = env::args().collect();
+  let interpreter = thread::Builder::new()
+    .stack_size(STACK_SIZE)
+    .spawn(run)
+    .expect("Failed to start the interpreter");
+
+  if interpreter.join().is_err() {
+    std::process::exit(101);
+  }
+}
+
+fn run() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let mut search_path = Vec::<PathBuf>::new();
+  let mut files = Vec::<&String>::new();
+  let mut checks = true;
+  let mut machine = false;
+  let mut overflow = None;
+
+  for arg in &args {
+    if let Some(dir) = arg.strip_prefix("--path=") {
+      search_path.push(PathBuf::from(dir));
+    }
+    else if arg == "--bigint" {
+      interpreter::integers::set_unbounded(true);
+    }
+    else if let Some(mode) = arg.strip_prefix("--overflow=") {
+      match interpreter::integers::Overflow::from_name(mode) {
+        Some(mode) => overflow = Some(mode),
+        None => {
+          println!("Modo de estouro {} desconhecido, use wrap, saturate ou error",mode);
+          return;
+        }
+      }
+    }
+    else if let Some(width) = arg.strip_prefix("--int-width=") {
+      match width {
+        "8" | "16" | "32" | "64" => interpreter::integers::set_width(width.parse().expect("Bad width")),
+        _ => {
+          println!("Largura de inteiro {} desconhecida, use 8, 16, 32 ou 64",width);
+          return;
+        }
+      }
+      machine = true;
+    }
+    else if arg == "--unsigned" {
+      interpreter::integers::set_unsigned(true);
+      machine = true;
+    }
+    else if arg == "--no-checks" {
+      checks = false;
+    }
+    else if arg.starts_with("--") {
+      println!("Opção {} desconhecida",arg);
+      return;
+    }
+    else {
+      files.push(arg);
+    }
+  }
+
+  if machine && interpreter::integers::unbounded() {
+    println!("--bigint não pode ser usado com --int-width ou --unsigned");
+    return;
+  }
+
+  // Emulating the machine integers wraps around like the machine, unless told otherwise
+  match overflow {
+    Some(overflow) => interpreter::integers::set_overflow(overflow),
+    None if machine => interpreter::integers::set_overflow(interpreter::integers::Overflow::Wrap),
+    None => {}
+  }
 
-  if args.len() != 2 {
-    println!("Usage: tiny <filename>.tiny");
+  if files.len() != 1 {
+    println!("Uso: tiny [--path=<dir>]... [--no-checks] [--bigint] [--overflow=wrap|saturate|error] [--int-width=8|16|32|64] [--unsigned] <arquivo>.tiny");
     return;
   }
 
-  let f: File = match File::open(&args[1]){
+  let f: File = match File::open(files[0]){
     Ok(file) => file,
     Err(error) => match error.kind() {  
-      ErrorKind::NotFound => panic!("File does not exist"),
-      ErrorKind::PermissionDenied => panic!("No permission to read file"),
-      _ => panic!("Unknow error"),
+      ErrorKind::NotFound => panic!("Arquivo não existe"),
+      ErrorKind::PermissionDenied => panic!("Sem permissão para ler o arquivo"),
+      _ => panic!("Erro desconhecido"),
       }
     };
 
-  let lexes = lexical::lexical_analizer::lexical_analysis(f);
-  syntatic::syntatic_analizer::start(lexes);
+  let lexes = lexical::lexical_analizer::lexical_analysis(f,files[0]);
+  syntatic::syntatic_analizer::start(lexes,Path::new(files[0]),search_path,checks);
 }
 
@@ -1 +1,2 @@
-pub mod syntatic_analizer;
\ No newline at end of file
+pub mod syntatic_analizer;
+pub mod type_checker;
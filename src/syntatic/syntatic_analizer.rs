@@ -1,7 +1,13 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
 
 use crate::lexical::Lexeme;
 use crate::lexical::tokens::Token;
+use crate::lexical::sources;
+use crate::lexical::lexical_analizer::lexical_analysis;
 
 use crate::interpreter::expressions::Expression;
 use crate::interpreter::expressions::ExprType;
@@ -9,49 +15,212 @@ use crate::interpreter::expressions::BoolOp;
 use crate::interpreter::expressions::IntOp;
 use crate::interpreter::expressions::Expr;
 use crate::interpreter::expressions::BinaryInt;
+use crate::interpreter::expressions::UnaryInt;
+use crate::interpreter::expressions::UnaryOp;
 use crate::interpreter::expressions::SingleBool;
+use crate::interpreter::expressions::LogicOp;
+use crate::interpreter::expressions::BinaryBool;
+use crate::interpreter::expressions::NotBool;
+use crate::interpreter::expressions::FuncCall;
+use crate::interpreter::expressions::Builtin;
+use crate::interpreter::expressions::BuiltinFunc;
+use crate::interpreter::expressions::ArrayLit;
+use crate::interpreter::expressions::Index;
+use crate::interpreter::expressions::Conditional;
+use crate::interpreter::expressions::RecordLit;
+use crate::interpreter::expressions::Field;
+use crate::interpreter::expressions::MapLit;
 
 use crate::interpreter::commands::Command;
 use crate::interpreter::commands::AssignCmd;
+use crate::interpreter::commands::Accessor;
 use crate::interpreter::commands::WhileCmd;
 use crate::interpreter::commands::IfCmd;
+use crate::interpreter::commands::ElifBranch;
+use crate::interpreter::commands::CaseCmd;
+use crate::interpreter::commands::CaseArm;
+use crate::interpreter::commands::ForCmd;
+use crate::interpreter::commands::ForInCmd;
+use crate::interpreter::commands::TryCmd;
+use crate::interpreter::commands::Contract;
+use crate::interpreter::commands::RepeatCmd;
+use crate::interpreter::commands::Function;
 use crate::interpreter::commands::cmd_exec;
 
-struct State {
+use crate::interpreter::memory::Memory;
+use crate::interpreter::integers;
+use crate::interpreter::errors::ERROR_RECORD;
+use crate::interpreter::errors::ERROR_FIELDS;
+
+use crate::syntatic::type_checker;
+use crate::interpreter::values::Value;
+use crate::interpreter::values::Variant;
+
+// What the files of a program share while they are parsed
+struct Program {
+  // Directories where imported files are looked for, after the one of the importing file
+  search_path: Vec::<PathBuf>,
+  // Files being parsed, each one imported by the previous
+  loading: Vec::<PathBuf>,
+  modules: HashMap::<String,Module>,
+  // Number of parameters of every function in the program
+  arities: HashMap::<String,usize>,
+  functions: HashMap::<String,Rc<Function>>,
+  // Field names of every record type, named like the functions
+  records: HashMap::<String,Vec::<String>>,
+  // Variant names of every enum, in the order of their ordinals
+  enums: HashMap::<String,Vec::<String>>,
+  // Whether asserts, invariants, requires and ensures are kept
+  checks: bool,
+}
+
+// An imported file, its functions are named <module>.<function>
+struct Module {
+  path: PathBuf,
+  constants: HashMap::<String,Value>,
+}
+
+struct State<'a> {
   pub lexes: Vec::<Lexeme>,
   pub current: usize,
-  pub line_number: u16,
+  pub program: &'a mut Program,
+  // The file being parsed and the modules it imports
+  pub path: PathBuf,
+  pub imports: Vec::<String>,
+  // Put before the names of the functions defined in the file, empty for the main one
+  pub prefix: String,
+  pub in_function: bool,
+  // Number of command lists around the command being parsed, 1 in the program body
+  pub depth: usize,
+  // Labels of the loops around the command being parsed, None when unlabeled
+  pub loops: Vec::<Option::<String>>,
+  // Values of the constants declared so far, replaced wherever their names are used
+  pub constants: HashMap::<String,Value>,
+  // Checked when the main program ends
+  pub ensures: Vec::<Contract>,
 }
 
-pub fn start(lexes: Vec::<Lexeme>){
-    let mut state = State {lexes,current:0,line_number:1};
-    let mut map = HashMap::<String,i32>::new();
-  
-    let cmds = proc_program(&mut state);
-    eat(&mut state,Token::EndOfFile);
+pub fn start(lexes: Vec::<Lexeme>, path: &Path, search_path: Vec::<PathBuf>, checks: bool){
+    let root = std::fs::canonicalize(path).unwrap_or(path.to_path_buf());
+    let mut program = Program {
+      search_path,
+      loading: vec![root],
+      modules: HashMap::new(),
+      arities: HashMap::new(),
+      functions: HashMap::new(),
+      records: HashMap::from([(ERROR_RECORD.to_string(),ERROR_FIELDS.map(String::from).to_vec())]),
+      enums: HashMap::new(),
+      checks
+    };
+
+    let (cmds,_) = proc_file(&mut program,lexes,path,String::new());
 
-    if let Command::Block(b) = cmds {
-      cmd_exec(b,&mut map);
+    type_checker::check(&cmds,&program.functions,&program.records);
+
+    let mut mem = Memory::new(program.functions);
+    cmd_exec(&cmds,&mut mem);
+}
+
+// Parses one of the files of the program, returning its commands and constants
+fn proc_file(program: &mut Program, lexes: Vec::<Lexeme>, path: &Path, prefix: String) -> (Vec::<Command>,HashMap::<String,Value>) {
+  scan_functions(&lexes,&prefix,&mut program.arities);
+  scan_records(&lexes,&prefix,program);
+
+  let mut state = State {
+    lexes,
+    current: 0,
+    program,
+    path: path.to_path_buf(),
+    imports: Vec::new(),
+    prefix,
+    in_function: false,
+    depth: 0,
+    loops: Vec::new(),
+    constants: HashMap::new(),
+    ensures: Vec::new()
+  };
+
+  let cmds = proc_program(&mut state);
+  eat(&mut state,Token::EndOfFile);
+
+  if let Command::Block(mut b) = cmds {
+    b.extend(state.ensures.drain(..).map(Command::Check));
+    return (b,state.constants);
+  }
+
+  panic!("Bad blocks");
+}
+
+// Collects the function headers beforehand so they can be called before being defined
+fn scan_functions(lexes: &[Lexeme], prefix: &str, arities: &mut HashMap::<String,usize>) {
+  for (i,lex) in lexes.iter().enumerate() {
+    if lex.ttype != Token::Func || i + 2 >= lexes.len() || lexes[i + 2].ttype != Token::LParen {
+      continue;
+    }
+
+    let name = &lexes[i + 1].token;
+    if BuiltinFunc::from_name(name).is_some() {
+      show_semantic_error(lex.line_number,&format!("{} é uma função pré-definida",name));
+    }
+
+    let params = lexes[i + 3..].iter()
+      .take_while(|l| l.ttype != Token::RParen && l.ttype != Token::EndOfFile)
+      .filter(|l| l.ttype == Token::Var)
+      .count();
+
+    if arities.insert(format!("{}{}",prefix,name),params).is_some() {
+      show_semantic_error(lex.line_number,&format!("Função {} definida mais de uma vez",name));
     }
+  }
 }
 
-fn show_error(lex: &Lexeme){
+// Records can also be used before being declared
+fn scan_records(lexes: &[Lexeme], prefix: &str, program: &mut Program) {
+  for (i,lex) in lexes.iter().enumerate() {
+    if lex.ttype != Token::Record || i + 1 >= lexes.len() {
+      continue;
+    }
+
+    let name = &lexes[i + 1].token;
+    let qualified = format!("{}{}",prefix,name);
+    if BuiltinFunc::from_name(name).is_some() || program.arities.contains_key(&qualified) {
+      show_semantic_error(lex.line_number,&format!("Registro {} tem o nome de uma função",name));
+    }
+
+    let fields: Vec::<String> = lexes[i + 2..].iter()
+      .take_while(|l| l.ttype != Token::Done && l.ttype != Token::EndOfFile)
+      .filter(|l| l.ttype == Token::Var)
+      .map(|l| l.token.clone())
+      .collect();
+
+    if program.records.insert(qualified,fields).is_some() {
+      show_semantic_error(lex.line_number,&format!("Registro {} definido mais de uma vez",name));
+    }
+  }
+}
+
+fn show_error(lex: &Lexeme) -> ! {
   match lex.ttype {
     Token::EndOfFile => {
-      println!("{}:: Fim de arquivo inesperado",lex.line_number);
+      println!("{}:: Fim de arquivo inesperado",sources::location(lex.line_number));
     },
-    _ => print!("{}:: Lexema não experado [{},{}]\n ",lex.line_number,lex.token,Token::to_string(&lex.ttype).expect("Bad token"))
+    _ => print!("{}:: Lexema não experado [{},{}]\n ",sources::location(lex.line_number),lex.token,Token::to_string(lex.ttype).expect("Bad token"))
   }
   std::process::exit(1);
 }
+
+fn show_semantic_error(line: u16, msg: &str) -> ! {
+  println!("{}:: {}",sources::location(line),msg);
+  std::process::exit(1);
+}
   
-fn eat(state: &mut State,_desired: Token){
+fn eat(state: &mut State,desired: Token){
   /*println!("Expected: {}, Found: ({},{})",
-           Token::to_string(&desired).expect("Bad token"),
+           Token::to_string(desired).expect("Bad token"),
            state.lexes[state.current].token,
-           Token::to_string(&state.lexes[state.current].ttype).expect("Bad token")); */
+           Token::to_string(state.lexes[state.current].ttype).expect("Bad token")); */
 
-  if matches!(&state.lexes[state.current].ttype, desired){
+  if state.lexes[state.current].ttype == desired {
     state.current += 1;
   }
   else {
@@ -59,59 +228,237 @@ fn eat(state: &mut State,_desired: Token){
   }
 }
 
-// <program>   ::= program <cmdlist>
+// <program>   ::= program { <import> ; } <cmdlist>
 fn proc_program(state: &mut State) -> Command {
   eat(state,Token::Program);
-  return proc_cmd_list(state);
+
+  while matches!(state.lexes[state.current].ttype, Token::Import) {
+    proc_import(state);
+    eat(state,Token::Semicolon);
+  }
+
+  proc_cmd_list(state)
+}
+
+// <import>    ::= import (<var> | string)
+//
+// A name stands for the file <var>.tiny. The file is looked for next to the importing
+// one, then in the search path. The functions and constants it defines are used as
+// <module>.<name>, where the module is the file name without the extension.
+fn proc_import(state: &mut State) {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Import);
+
+  let file = match state.lexes[state.current].ttype {
+    Token::Var => format!("{}.tiny",state.lexes[state.current].token),
+    Token::Str => state.lexes[state.current].token.clone(),
+    _ => show_error(&state.lexes[state.current])
+  };
+  state.current += 1;
+
+  let dir = state.path.parent().map(Path::to_path_buf).unwrap_or_default();
+  let found = std::iter::once(&dir).chain(&state.program.search_path)
+    .map(|dir| dir.join(&file))
+    .find(|path| path.is_file());
+
+  let Some(found) = found else {
+    show_semantic_error(line,&format!("Arquivo {} não encontrado",file));
+  };
+  let path = std::fs::canonicalize(&found).unwrap_or(found.clone());
+  let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+  if let Some(i) = state.program.loading.iter().position(|loading| *loading == path) {
+    let cycle: Vec::<String> = state.program.loading[i..].iter().chain([&path])
+      .map(|path| path.display().to_string())
+      .collect();
+    show_semantic_error(line,&format!("Importação circular: {}",cycle.join(" -> ")));
+  }
+
+  match state.program.modules.get(&name) {
+    Some(module) if module.path != path => show_semantic_error(
+      line,
+      &format!("Dois módulos chamados {}: {} e {}",name,module.path.display(),path.display())
+    ),
+    Some(_) => {},
+    None => {
+      let Ok(f) = File::open(&found) else {
+        show_semantic_error(line,&format!("Não foi possível ler o arquivo {}",file));
+      };
+      let lexes = lexical_analysis(f,&found.display().to_string());
+
+      state.program.loading.push(path.clone());
+      let (_,constants) = proc_file(state.program,lexes,&found,format!("{}.",name));
+      state.program.loading.pop();
+
+      state.program.modules.insert(name.clone(),Module{path,constants});
+    }
+  }
+
+  if !state.imports.contains(&name) {
+    state.imports.push(name);
+  }
+}
+
+// Whether a module name and a dot start at the current token
+fn is_qualified(state: &State) -> bool {
+  matches!(state.lexes[state.current].ttype, Token::Var) &&
+    matches!(state.lexes[state.current + 1].ttype, Token::Dot) &&
+    state.imports.contains(&state.lexes[state.current].token)
+}
+
+// Whether a constant followed by : , or .. starts the next arm of a case, instead of a command
+fn is_case_label(state: &State) -> bool {
+  let (constant,next) = if is_qualified(state) {
+    (true,state.current + 3)
+  }
+  else {
+    (state.constants.contains_key(&state.lexes[state.current].token),state.current + 1)
+  };
+
+  constant && state.lexes.get(next).is_some_and(|lex| matches!(lex.ttype, Token::Colon | Token::Comma | Token::Range))
+}
+
+fn is_call(state: &State) -> bool {
+  let call_at = if is_qualified(state) { state.current + 3 } else { state.current + 1 };
+
+  matches!(state.lexes[state.current].ttype, Token::Var) &&
+    state.lexes.get(call_at).is_some_and(|lex| lex.ttype == Token::LParen)
 }
   
   // <cmdlist>   ::= <cmd> { <cmd> }
 fn proc_cmd_list(state: &mut State) -> Command {
+  state.depth += 1;
   let mut cmds = Vec::<Command>::new();
   cmds.push(proc_cmd(state));
 
-  while matches!(state.lexes[state.current].ttype, Token::Var) ||
+  while (matches!(state.lexes[state.current].ttype, Token::Var) && !is_case_label(state)) ||
     matches!(state.lexes[state.current].ttype, Token::Output) ||
     matches!(state.lexes[state.current].ttype, Token::If) ||
-    matches!(state.lexes[state.current].ttype, Token::While) {
+    matches!(state.lexes[state.current].ttype, Token::Case) ||
+    matches!(state.lexes[state.current].ttype, Token::While) ||
+    matches!(state.lexes[state.current].ttype, Token::Func) ||
+    matches!(state.lexes[state.current].ttype, Token::Record) ||
+    matches!(state.lexes[state.current].ttype, Token::Enum) ||
+    matches!(state.lexes[state.current].ttype, Token::Return) ||
+    matches!(state.lexes[state.current].ttype, Token::Declare) ||
+    matches!(state.lexes[state.current].ttype, Token::Const) ||
+    matches!(state.lexes[state.current].ttype, Token::For) ||
+    matches!(state.lexes[state.current].ttype, Token::Repeat) ||
+    matches!(state.lexes[state.current].ttype, Token::Break) ||
+    matches!(state.lexes[state.current].ttype, Token::Continue) ||
+    matches!(state.lexes[state.current].ttype, Token::Try) ||
+    matches!(state.lexes[state.current].ttype, Token::Raise) ||
+    matches!(state.lexes[state.current].ttype, Token::Assert) ||
+    matches!(state.lexes[state.current].ttype, Token::Requires) ||
+    matches!(state.lexes[state.current].ttype, Token::Ensures) {
 
     cmds.push(proc_cmd(state));
   }
+  state.depth -= 1;
   
-  return Command::Block(cmds);
+  Command::Block(cmds)
 }
   
-// <cmd>       ::= (<assign> | <output> | <if> | <while>) ;
+// <cmd>       ::= (<assign> | <decl> | <constdecl> | <record> | <enum> | <call> | <output> | <if> |
+//                  <case> | [ <label> ] <loop> | <break> | <continue> | <func> | <return> |
+//                  <try> | <raise> | <assert> | <requires> | <ensures>) ;
+// <loop>      ::= <while> | <for> | <repeat>
 fn proc_cmd(state: &mut State) -> Command {
   let mut cmd: Command = Command::Block(Vec::<Command>::new());
 
-  if matches!(state.lexes[state.current].ttype, Token::Var) {
+  if !state.prefix.is_empty() && !state.in_function &&
+    !matches!(state.lexes[state.current].ttype, Token::Func | Token::Const | Token::Record | Token::Enum) {
+    show_semantic_error(state.lexes[state.current].line_number,"Módulos importados só podem definir funções, constantes e tipos");
+  }
+
+  if is_call(state) {
+    cmd = Command::Call(proc_call(state));
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Var) &&
+    matches!(state.lexes[state.current + 1].ttype, Token::Colon) {
+    cmd = proc_labeled(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Var) {
     cmd = proc_assign(state);
   }
+  else if matches!(state.lexes[state.current].ttype, Token::Declare) {
+    cmd = proc_decl(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Const) {
+    proc_const_decl(state);
+  }
   else if matches!(state.lexes[state.current].ttype, Token::Output) {
     cmd = proc_output(state);
   }
   else if matches!(state.lexes[state.current].ttype, Token::If) {
     cmd = proc_if(state);
   }
+  else if matches!(state.lexes[state.current].ttype, Token::Case) {
+    cmd = proc_case(state);
+  }
   else if matches!(state.lexes[state.current].ttype, Token::While) {
-    cmd = proc_while(state);
+    cmd = proc_while(state,None);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::For) {
+    cmd = proc_for(state,None);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Repeat) {
+    cmd = proc_repeat(state,None);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Break) ||
+    matches!(state.lexes[state.current].ttype, Token::Continue) {
+    cmd = proc_break(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Func) {
+    proc_func(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Record) {
+    proc_record(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Enum) {
+    proc_enum(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Return) {
+    cmd = proc_return(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Try) {
+    cmd = proc_try(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Raise) {
+    cmd = proc_raise(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Assert) {
+    cmd = proc_assert(state);
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Requires | Token::Ensures) {
+    cmd = proc_program_contract(state);
   }
   else {
     show_error(&state.lexes[state.current]);
   }
   eat(state,Token::Semicolon);
 
-  return cmd;
+  cmd
 }
 
-// <if>        ::= if <boolexpr> then <cmdlist> [ else <cmdlist> ] done
+// <if>        ::= if <expr> then <cmdlist> { elif <expr> then <cmdlist> } [ else <cmdlist> ] done
 fn proc_if(state: &mut State) -> Command {
   eat(state,Token::If);
-  let condition = proc_bool_expr(state,false);
+  let condition = proc_expr(state);
   
   eat(state,Token::Then);
   let then_cmds = proc_cmd_list(state);
+
+  let mut elifs = Vec::<ElifBranch>::new();
+  while matches!(state.lexes[state.current].ttype, Token::Elif) {
+    state.current += 1;
+    let condition = proc_expr(state);
+
+    eat(state,Token::Then);
+    if let Command::Block(cmds) = proc_cmd_list(state) {
+      elifs.push(ElifBranch{condition,cmds});
+    }
+  }
   
   let mut else_cmds = Command::Block(Vec::<Command>::new());
 
@@ -124,214 +471,1115 @@ fn proc_if(state: &mut State) -> Command {
 
   if let Command::Block(i) = then_cmds {
     if let Command::Block(e) = else_cmds{
-      return Command::If(IfCmd{condition,then_cmds:i,else_cmds:e});
+      return Command::If(IfCmd{condition,then_cmds:i,elifs,else_cmds:e});
     }
   }
 
   panic!("Bad blocks");
 }
   
-// <while>     ::= while <boolexpr> do <cmdlist> done
-fn proc_while(state: &mut State) -> Command {
+// <case>      ::= case <expr> of { <arm> } [ else : <cmdlist> ] done
+// <arm>       ::= <range> { , <range> } : <cmdlist>
+// <range>     ::= <caseconst> [ .. <caseconst> ]
+fn proc_case(state: &mut State) -> Command {
+  let line_number = state.lexes[state.current].line_number;
+  eat(state,Token::Case);
+  let expr = proc_expr(state);
+  eat(state,Token::Of);
+
+  let mut arms = Vec::<CaseArm>::new();
+  let mut seen = Vec::<(i128,i128)>::new();
+  let mut kind = None;
+
+  while !matches!(state.lexes[state.current].ttype, Token::Else | Token::Done) {
+    let mut ranges = Vec::<(i128,i128)>::new();
+
+    loop {
+      let line = state.lexes[state.current].line_number;
+      let (low,low_kind) = proc_case_const(state);
+      let (mut high,mut high_kind) = (low,low_kind.clone());
+
+      if matches!(state.lexes[state.current].ttype, Token::Range) {
+        state.current += 1;
+        (high,high_kind) = proc_case_const(state);
+      }
+
+      // Every value of the case is an integer, or every one is a variant of the same enum
+      if low_kind != high_kind || kind.as_ref().is_some_and(|kind| *kind != low_kind) {
+        show_semantic_error(line,"Valores de tipos diferentes no case");
+      }
+      kind = Some(low_kind);
+
+      let label = |value: i128| case_label(state,kind.as_ref().expect("No kind"),value);
+      if high < low {
+        show_semantic_error(line,&format!("Intervalo {}..{} vazio",label(low),label(high)));
+      }
+
+      if let Some((l,h)) = seen.iter().find(|(l,h)| low <= *h && *l <= high) {
+        let repeated = if low <= *l {*l} else {low};
+        let arm = if l == h {label(*l)} else {format!("{}..{}",label(*l),label(*h))};
+        show_semantic_error(line,&format!("Valor {} repetido no case (já coberto por {})",label(repeated),arm));
+      }
+      seen.push((low,high));
+      ranges.push((low,high));
+
+      if !matches!(state.lexes[state.current].ttype, Token::Comma) {
+        break;
+      }
+      state.current += 1;
+    }
+
+    eat(state,Token::Colon);
+    if let Command::Block(cmds) = proc_cmd_list(state) {
+      arms.push(CaseArm{ranges,cmds});
+    }
+  }
+
+  let mut else_cmds = Vec::<Command>::new();
+  if matches!(state.lexes[state.current].ttype, Token::Else) {
+    state.current += 1;
+    eat(state,Token::Colon);
+
+    if let Command::Block(cmds) = proc_cmd_list(state) {
+      else_cmds = cmds;
+    }
+  }
+
+  eat(state,Token::Done);
+
+  Command::Case(CaseCmd{expr,kind: kind.flatten(),arms,else_cmds,line_number})
+}
+
+// Shows a case value as written, enum variants are kept as ordinals
+fn case_label(state: &State, kind: &Option::<String>, value: i128) -> String {
+  match kind {
+    Some(ty) => state.program.enums[ty][value as usize].clone(),
+    None => value.to_string()
+  }
+}
+
+// <caseconst> ::= [ - ] number | <var> [ . <var> ]
+//
+// A name must be an integer constant or an enum variant, which is returned as its
+// ordinal along with the name of the enum
+fn proc_case_const(state: &mut State) -> (i128,Option::<String>) {
+  let line = state.lexes[state.current].line_number;
+
+  if matches!(state.lexes[state.current].ttype, Token::Var) {
+    let token = state.lexes[state.current].token.clone();
+    let Expression::Ex(ex) = proc_var(state) else {
+      panic!("Bad name");
+    };
+
+    return match (ex.expr,ex.value) {
+      (ExprType::ConstInt,value @ (Value::Int(_) | Value::Big(_))) => (case_value(line,&token,&value),None),
+      (ExprType::ConstEnum,Value::Enum(variant)) => (variant.ordinal as i128,Some(variant.ty)),
+      _ => show_semantic_error(line,&format!("{} não é uma constante inteira ou variante de enum",token))
+    };
+  }
+
+  let negative = matches!(state.lexes[state.current].ttype, Token::Sub);
+  if negative {
+    state.current += 1;
+  }
+
+  // Read like any other literal, so it may have a base and must fit in the width
+  let token = state.lexes[state.current].token.clone();
+  let Expression::Ex(ex) = proc_const(state,negative) else {
+    panic!("Bad constant");
+  };
+
+  match ex.value {
+    value @ (Value::Int(_) | Value::Big(_)) => (case_value(line,&token,&value),None),
+    _ => show_semantic_error(line,&format!("{} não é uma constante inteira",token))
+  }
+}
+
+// Unsigned 64 bit integers fit too, only --bigint makes larger ones
+fn case_value(line: u16, token: &str, value: &Value) -> i128 {
+  match value {
+    Value::Int(value) => *value as i128,
+    Value::Big(value) => match value.to_i128() {
+      Some(value) => value,
+      None => show_semantic_error(line,&format!("Inteiro {} grande demais para um case",token))
+    },
+    _ => unreachable!()
+  }
+}
+
+// <try>       ::= try <cmdlist> catch <var> <cmdlist> [ finally <cmdlist> ] done
+//
+// The variable after catch holds an Error record with the kind, code, line and
+// message of the error
+fn proc_try(state: &mut State) -> Command {
+  eat(state,Token::Try);
+  let body = proc_block(state);
+
+  eat(state,Token::Catch);
+  let var = state.lexes[state.current].token.clone();
+  check_not_constant(state,&var,state.lexes[state.current].line_number);
+  eat(state,Token::Var);
+  let handler = proc_block(state);
+
+  let mut finally = Vec::<Command>::new();
+  if matches!(state.lexes[state.current].ttype, Token::Finally) {
+    state.current += 1;
+    finally = proc_block(state);
+  }
+
+  eat(state,Token::Done);
+
+  Command::Try(TryCmd{body,var,handler,finally})
+}
+
+fn proc_block(state: &mut State) -> Vec::<Command> {
+  if let Command::Block(cmds) = proc_cmd_list(state) {
+    return cmds;
+  }
+
+  panic!("Bad blocks");
+}
+
+// <raise>     ::= raise <expr>
+//
+// Raises an error with an integer code, or raises again an error caught before
+fn proc_raise(state: &mut State) -> Command {
+  eat(state,Token::Raise);
+
+  Command::Raise(proc_expr(state))
+}
+
+// <assert>    ::= assert <contract>
+fn proc_assert(state: &mut State) -> Command {
+  eat(state,Token::Assert);
+  let contract = proc_contract(state,"Asserção");
+
+  if !state.program.checks {
+    return Command::Block(Vec::new());
+  }
+  Command::Check(contract)
+}
+
+// <requires>  ::= requires <contract>
+// <ensures>   ::= ensures <contract>
+//
+// Only in the body of the main program, outside functions and blocks. A requires is
+// checked where it is, an ensures when the program ends.
+fn proc_program_contract(state: &mut State) -> Command {
+  let line = state.lexes[state.current].line_number;
+  let ensures = matches!(state.lexes[state.current].ttype, Token::Ensures);
+  let keyword = state.lexes[state.current].token.clone();
+  state.current += 1;
+
+  if state.in_function {
+    show_semantic_error(line,&format!("{} só pode ser usado fora de funções",keyword));
+  }
+  if state.depth > 1 {
+    show_semantic_error(line,&format!("{} só pode ser usado no corpo do programa, fora de blocos",keyword));
+  }
+
+  let contract = proc_contract(state,if ensures { "Pós-condição" } else { "Pré-condição" });
+
+  if !state.program.checks {
+    return Command::Block(Vec::new());
+  }
+  if ensures {
+    state.ensures.push(contract);
+    return Command::Block(Vec::new());
+  }
+  Command::Check(contract)
+}
+
+// <contract>  ::= <expr> [ , string ]
+fn proc_contract(state: &mut State, clause: &'static str) -> Contract {
+  let line_number = state.lexes[state.current].line_number;
+  let cond = proc_expr(state);
+
+  let mut message = None;
+  if matches!(state.lexes[state.current].ttype, Token::Comma) {
+    eat(state,Token::Comma);
+    message = Some(state.lexes[state.current].token.clone());
+    eat(state,Token::Str);
+  }
+
+  Contract{clause, cond, message, line_number}
+}
+
+// <label>     ::= <var> :
+fn proc_labeled(state: &mut State) -> Command {
+  let line = state.lexes[state.current].line_number;
+  let label = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+  eat(state,Token::Colon);
+
+  if state.loops.contains(&Some(label.clone())) {
+    show_semantic_error(line,&format!("Rótulo {} já usado em um laço externo",label));
+  }
+
+  match state.lexes[state.current].ttype {
+    Token::While => proc_while(state,Some(label)),
+    Token::For => proc_for(state,Some(label)),
+    Token::Repeat => proc_repeat(state,Some(label)),
+    _ => show_error(&state.lexes[state.current])
+  }
+}
+
+// <break>     ::= break [ <var> ]
+// <continue>  ::= continue [ <var> ]
+fn proc_break(state: &mut State) -> Command {
+  let line = state.lexes[state.current].line_number;
+  let is_break = matches!(state.lexes[state.current].ttype, Token::Break);
+  let keyword = state.lexes[state.current].token.clone();
+  state.current += 1;
+
+  let mut label = None;
+  if matches!(state.lexes[state.current].ttype, Token::Var) {
+    label = Some(state.lexes[state.current].token.clone());
+    state.current += 1;
+  }
+
+  if state.loops.is_empty() {
+    show_semantic_error(line,&format!("{} fora de um laço",keyword));
+  }
+  if let Some(name) = &label {
+    if !state.loops.contains(&label) {
+      show_semantic_error(line,&format!("Laço {} não encontrado",name));
+    }
+  }
+
+  if is_break {
+    Command::Break(label)
+  }
+  else {
+    Command::Continue(label)
+  }
+}
+
+// Parses the body of a loop, where break and continue may refer to it
+fn proc_loop_body(state: &mut State, label: &Option::<String>) -> Vec::<Command> {
+  state.loops.push(label.clone());
+  let cmds = proc_cmd_list(state);
+  state.loops.pop();
+
+  if let Command::Block(b) = cmds {
+    return b;
+  }
+
+  panic!("Bad blocks");
+}
+
+// <while>     ::= while <expr> { invariant <contract> } do <cmdlist> done
+//
+// The invariants are checked before every test of the condition
+fn proc_while(state: &mut State, label: Option::<String>) -> Command {
   eat(state,Token::While);
-  let cond = proc_bool_expr(state,false);
+  let cond = proc_expr(state);
+
+  let mut invariants = Vec::<Contract>::new();
+  while matches!(state.lexes[state.current].ttype, Token::Invariant) {
+    eat(state,Token::Invariant);
+    invariants.push(proc_contract(state,"Invariante"));
+  }
+  if !state.program.checks {
+    invariants.clear();
+  }
   
   eat(state,Token::Do);
-  let cmds = proc_cmd_list(state);
+  let cmds = proc_loop_body(state,&label);
   
   eat(state,Token::Done);
 
-  if let Command::Block(b) = cmds {
-    return Command::While(WhileCmd{cond, cmds: b});
+  Command::While(WhileCmd{cond, invariants, cmds, label})
+}
+
+// <for>       ::= for <var> = <expr> to <expr> [ step <expr> ] do <cmdlist> done |
+//                 for <var> in <expr> do <cmdlist> done
+//
+// A negative step counts down to the end value. The second form goes over the keys
+// of a map, in the order they were added, or over the elements of an array.
+fn proc_for(state: &mut State, label: Option::<String>) -> Command {
+  let line_number = state.lexes[state.current].line_number;
+  eat(state,Token::For);
+
+  let var = state.lexes[state.current].token.clone();
+  check_not_constant(state,&var,line_number);
+  eat(state,Token::Var);
+
+  if matches!(state.lexes[state.current].ttype, Token::In) {
+    state.current += 1;
+    let expr = proc_expr(state);
+
+    eat(state,Token::Do);
+    let cmds = proc_loop_body(state,&label);
+    eat(state,Token::Done);
+
+    return Command::ForIn(ForInCmd{var,expr,cmds,label,line_number});
+  }
+
+  eat(state,Token::Assign);
+  let start = proc_expr(state);
+
+  eat(state,Token::To);
+  let end = proc_expr(state);
+
+  let mut step = Expression::Ex(Expr {expr: ExprType::ConstInt, value: Value::Int(1), name: String::new(), line_number});
+  if matches!(state.lexes[state.current].ttype, Token::Step) {
+    state.current += 1;
+    step = proc_expr(state);
+  }
+
+  eat(state,Token::Do);
+  let cmds = proc_loop_body(state,&label);
+
+  eat(state,Token::Done);
+
+  Command::For(ForCmd{var,start,end,step,cmds,label,line_number})
+}
+
+// <repeat>    ::= repeat <cmdlist> until <expr>
+fn proc_repeat(state: &mut State, label: Option::<String>) -> Command {
+  eat(state,Token::Repeat);
+  let cmds = proc_loop_body(state,&label);
+
+  eat(state,Token::Until);
+  let cond = proc_expr(state);
+
+  Command::Repeat(RepeatCmd{cmds,cond,label})
+}
+
+// <func>      ::= func <var> ( [ <var> { , <var> } ] ) <cmdlist> done
+//
+// Functions are kept apart from the commands, they can only be defined at the
+// top level of the program and run in their own frame
+fn proc_func(state: &mut State) {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Func);
+
+  if state.in_function {
+    show_semantic_error(line,"Funções não podem ser definidas dentro de funções");
+  }
+
+  let name = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+  eat(state,Token::LParen);
+
+  let mut params = Vec::<String>::new();
+  if !matches!(state.lexes[state.current].ttype, Token::RParen) {
+    params.push(state.lexes[state.current].token.clone());
+    eat(state,Token::Var);
+
+    while matches!(state.lexes[state.current].ttype, Token::Comma) {
+      state.current += 1;
+      params.push(state.lexes[state.current].token.clone());
+      eat(state,Token::Var);
+    }
+  }
+  eat(state,Token::RParen);
+
+  for (i,param) in params.iter().enumerate() {
+    check_not_constant(state,param,line);
+    if params[..i].contains(param) {
+      show_semantic_error(line,&format!("Parâmetro {} repetido na função {}",param,name));
+    }
+  }
+
+  // Loops around the definition are not visible from the body
+  let loops = std::mem::take(&mut state.loops);
+  state.in_function = true;
+  let body = proc_cmd_list(state);
+  state.in_function = false;
+  state.loops = loops;
+
+  eat(state,Token::Done);
+
+  if let Command::Block(body) = body {
+    let name = format!("{}{}",state.prefix,name);
+    state.program.functions.insert(name.clone(),Rc::new(Function{name,params,body}));
+    return;
   }
 
   panic!("Bad blocks");
 }
 
-// <assign>    ::= <var> = <intexpr>
+// <record>    ::= record <var> <var> { , <var> } done
+//
+// Values are built calling the record like a function, with one argument per field
+fn proc_record(state: &mut State) {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Record);
+
+  if state.in_function {
+    show_semantic_error(line,"Registros não podem ser definidos dentro de funções");
+  }
+
+  let name = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+
+  let mut fields = vec![state.lexes[state.current].token.clone()];
+  eat(state,Token::Var);
+
+  while matches!(state.lexes[state.current].ttype, Token::Comma) {
+    state.current += 1;
+
+    let field = state.lexes[state.current].token.clone();
+    if fields.contains(&field) {
+      show_semantic_error(line,&format!("Campo {} repetido no registro {}",field,name));
+    }
+    fields.push(field);
+    eat(state,Token::Var);
+  }
+
+  eat(state,Token::Done);
+}
+
+// <enum>      ::= enum <var> <var> { , <var> } done
+//
+// Every variant becomes a constant holding a value of the enum, values of different
+// enums can't be compared
+fn proc_enum(state: &mut State) {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Enum);
+
+  let name = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+
+  let ty = format!("{}{}",state.prefix,name);
+  if state.program.records.contains_key(&ty) || state.program.enums.contains_key(&ty) ||
+    state.program.arities.contains_key(&ty) || BuiltinFunc::from_name(&name).is_some() {
+    show_semantic_error(line,&format!("Nome {} já usado por outro tipo ou função",name));
+  }
+
+  let mut variants = Vec::<String>::new();
+  loop {
+    let variant = state.lexes[state.current].token.clone();
+    if variants.contains(&variant) {
+      show_semantic_error(line,&format!("Variante {} repetida no enum {}",variant,name));
+    }
+    check_not_constant(state,&variant,line);
+    eat(state,Token::Var);
+
+    let value = Value::Enum(Variant{ty: ty.clone(),name: variant.clone(),ordinal: variants.len() as i64});
+    state.constants.insert(variant.clone(),value);
+    variants.push(variant);
+
+    if !matches!(state.lexes[state.current].ttype, Token::Comma) {
+      break;
+    }
+    state.current += 1;
+  }
+
+  eat(state,Token::Done);
+  state.program.enums.insert(ty,variants);
+}
+
+// Field names are checked against every record, the type checker
+// checks them again when the record type of the value is known
+fn proc_field(state: &mut State) -> String {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Dot);
+
+  let name = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+
+  if !state.program.records.values().any(|fields| fields.contains(&name)) {
+    show_semantic_error(line,&format!("Campo {} não existe em nenhum registro",name));
+  }
+
+  name
+}
+
+// <return>    ::= return [ <expr> ]
+fn proc_return(state: &mut State) -> Command {
+  let line = state.lexes[state.current].line_number;
+  eat(state,Token::Return);
+
+  if !state.in_function {
+    show_semantic_error(line,"Return fora de uma função");
+  }
+
+  if matches!(state.lexes[state.current].ttype, Token::Semicolon) {
+    return Command::Return(Expression::Ex(Expr {expr: ExprType::ConstInt, value: Value::Int(0), name: String::new(), line_number: line}));
+  }
+
+  Command::Return(proc_expr(state))
+}
+
+// <call>      ::= [ <var> . ] <var> ( [ <expr> { , <expr> } ] )
+fn proc_call(state: &mut State) -> Expression {
+  let line_number = state.lexes[state.current].line_number;
+  let mut module = None;
+  if is_qualified(state) {
+    module = Some(state.lexes[state.current].token.clone());
+    state.current += 2;
+  }
+
+  let name = state.lexes[state.current].token.clone();
+  eat(state,Token::Var);
+  eat(state,Token::LParen);
+
+  let mut args = Vec::<Expression>::new();
+  if !matches!(state.lexes[state.current].ttype, Token::RParen) {
+    args.push(proc_expr(state));
+
+    while matches!(state.lexes[state.current].ttype, Token::Comma) {
+      state.current += 1;
+      args.push(proc_expr(state));
+    }
+  }
+  eat(state,Token::RParen);
+
+  let builtin = BuiltinFunc::from_name(&name).filter(|_| module.is_none());
+  if let Some((func,arity)) = builtin {
+    if arity != args.len() {
+      show_semantic_error(
+        line_number,
+        &format!("Função {} espera {} argumento(s), recebeu {}",name,arity,args.len())
+      );
+    }
+    if matches!(func, BuiltinFunc::Remove) && !args[0].is_place() {
+      show_semantic_error(line_number,"O primeiro argumento de remove deve ser uma variável");
+    }
+    return Expression::Bt(Builtin{func,args,line_number});
+  }
+
+  let name = match module {
+    Some(module) => format!("{}.{}",module,name),
+    None => format!("{}{}",state.prefix,name)
+  };
+
+  if let Some(fields) = state.program.records.get(&name) {
+    if fields.len() != args.len() {
+      show_semantic_error(
+        line_number,
+        &format!("Registro {} tem {} campo(s), recebeu {}",name,fields.len(),args.len())
+      );
+    }
+    return Expression::Rl(RecordLit{name,fields: fields.clone(),args,line_number});
+  }
+
+  match state.program.arities.get(&name) {
+    None => show_semantic_error(line_number,&format!("Função {} não definida",name)),
+    Some(arity) if *arity != args.len() => show_semantic_error(
+      line_number,
+      &format!("Função {} espera {} argumento(s), recebeu {}",name,arity,args.len())
+    ),
+    _ => {}
+  }
+
+  Expression::Fc(FuncCall{name,args,line_number})
+}
+
+// <assign>    ::= <target> <assignop> <expr> | <target> (++ | --) |
+//                 <target> , <target> { , <target> } = <expr> , <expr> { , <expr> }
+// <target>    ::= <var> { [ <expr> ] | . <var> }
+// <assignop>  ::= = | += | -= | *= | /= | %= | ^=
 fn proc_assign(state: &mut State) -> Command {
+  let mut target = proc_target(state);
+
+  let op = match state.lexes[state.current].ttype {
+    Token::Assign => None,
+    Token::AddAssign | Token::Increment => Some(IntOp::Add),
+    Token::SubAssign | Token::Decrement => Some(IntOp::Sub),
+    Token::MulAssign => Some(IntOp::Mul),
+    Token::DivAssign => Some(IntOp::Div),
+    Token::ModAssign => Some(IntOp::Mod),
+    Token::PowAssign => Some(IntOp::Pow),
+    Token::Comma => return proc_multi_assign(state,target),
+    _ => show_error(&state.lexes[state.current])
+  };
+
+  let ttype = state.lexes[state.current].ttype;
+  state.current += 1;
+
+  // x++ is the same as x += 1
+  target.expr = if matches!(ttype, Token::Increment | Token::Decrement) {
+    Expression::Ex(Expr {expr: ExprType::ConstInt, value: Value::Int(1), name: String::new(), line_number: target.line_number})
+  }
+  else {
+    proc_expr(state)
+  };
+  target.op = op;
+
+  Command::Assignment(target)
+}
+
+// Every value is computed before any of the targets changes, so a, b = b, a swaps them
+fn proc_multi_assign(state: &mut State, first: AssignCmd) -> Command {
+  let line_number = first.line_number;
+  let mut targets = vec![first];
+
+  while matches!(state.lexes[state.current].ttype, Token::Comma) {
+    state.current += 1;
+    targets.push(proc_target(state));
+  }
+
+  eat(state,Token::Assign);
+
+  let mut exprs = vec![proc_expr(state)];
+  while matches!(state.lexes[state.current].ttype, Token::Comma) {
+    state.current += 1;
+    exprs.push(proc_expr(state));
+  }
+
+  if exprs.len() != targets.len() {
+    show_semantic_error(
+      line_number,
+      &format!("Atribuição múltipla com {} variável(is) e {} valor(es)",targets.len(),exprs.len())
+    );
+  }
+
+  for (target,expr) in targets.iter_mut().zip(exprs) {
+    target.expr = expr;
+  }
+
+  Command::MultiAssignment(targets)
+}
+
+// Parses the left side of an assignment, the expression is filled in by the caller
+fn proc_target(state: &mut State) -> AssignCmd {
+  let line_number = state.lexes[state.current].line_number;
+  if is_qualified(state) {
+    show_semantic_error(line_number,"Constantes de módulos não podem ser alteradas");
+  }
+  check_not_constant(state,&state.lexes[state.current].token,line_number);
   let var = proc_var(state);
+
+  let mut path = Vec::<Accessor>::new();
+  loop {
+    match state.lexes[state.current].ttype {
+      Token::LBracket => {
+        state.current += 1;
+        path.push(Accessor::Index(proc_expr(state)));
+        eat(state,Token::RBracket);
+      },
+      Token::Dot => path.push(Accessor::Field(proc_field(state))),
+      _ => break
+    }
+  }
+
+  let Expression::Ex(ex) = var else {
+    panic!("Bad name");
+  };
+
+  let expr = Expression::Ex(Expr {expr: ExprType::ConstInt, value: Value::Int(0), name: String::new(), line_number});
+  AssignCmd{expr,var: ex.name,path,op: None,line_number}
+}
+
+// <decl>      ::= var <var> = <expr>
+//
+// The variable only exists until the end of the enclosing block
+fn proc_decl(state: &mut State) -> Command {
+  let line_number = state.lexes[state.current].line_number;
+  eat(state,Token::Declare);
+
+  let var = state.lexes[state.current].token.clone();
+  check_not_constant(state,&var,line_number);
+  eat(state,Token::Var);
   eat(state,Token::Assign);
-  
-  let expr = proc_int_expr(state);
-  
-  if let Expression::Ex(ex) = var {
-    return Command::Assignment(AssignCmd{expr,var: ex.name});
+
+  let expr = proc_expr(state);
+
+  Command::Declaration(AssignCmd{expr,var,path: Vec::new(),op: None,line_number})
+}
+
+// <constdecl> ::= const <var> = <expr>
+//
+// The value is computed while parsing, so the expression may only use literals,
+// other constants and the pre-defined functions. The name can be used from here
+// to the end of the program, functions included.
+fn proc_const_decl(state: &mut State) {
+  let line_number = state.lexes[state.current].line_number;
+  eat(state,Token::Const);
+
+  let name = state.lexes[state.current].token.clone();
+  check_not_constant(state,&name,line_number);
+  eat(state,Token::Var);
+  eat(state,Token::Assign);
+
+  let expr = proc_expr(state);
+  if !is_constant(&expr) {
+    show_semantic_error(line_number,&format!("Valor da constante {} não é conhecido antes da execução",name));
   }
 
-  panic!("Bad name");
+  let value = expr.exec(&mut Memory::new(HashMap::new()));
+  if matches!(value, Value::Array(_) | Value::Record(_) | Value::Map(_)) {
+    show_semantic_error(line_number,&format!("Constante {} não pode ser um vetor, registro ou mapa",name));
+  }
+
+  state.constants.insert(name,value);
+}
+
+fn check_not_constant(state: &State, name: &str, line: u16) {
+  if state.constants.contains_key(name) {
+    show_semantic_error(line,&format!("Constante {} não pode ser alterada",name));
+  }
+}
+
+// Whether the expression gives the same value every time, without reading
+// variables, input or calling user functions
+fn is_constant(expr: &Expression) -> bool {
+  match expr {
+    Expression::Ex(ex) => !matches!(ex.expr, ExprType::Var | ExprType::ReadInt),
+    Expression::Un(un) => is_constant(&un.expr),
+    Expression::Bi(bi) => is_constant(&bi.left) && is_constant(&bi.right),
+    Expression::Sb(sb) => is_constant(&sb.left) && is_constant(&sb.right),
+    Expression::Bb(bb) => is_constant(&bb.left) && is_constant(&bb.right),
+    Expression::Nb(nb) => is_constant(&nb.expr),
+    Expression::Fc(_) => false,
+    Expression::Bt(bt) => bt.args.iter().all(is_constant),
+    Expression::Al(al) => al.items.iter().all(is_constant),
+    Expression::Ix(ix) => is_constant(&ix.array) && is_constant(&ix.index),
+    Expression::Cd(cd) => is_constant(&cd.cond) && is_constant(&cd.then_expr) && is_constant(&cd.else_expr),
+    Expression::Rl(rl) => rl.args.iter().all(is_constant),
+    Expression::Fd(fd) => is_constant(&fd.record),
+    Expression::Ml(ml) => ml.entries.iter().all(|(key,value)| is_constant(key) && is_constant(value))
+  }
 }
 
-// <output>    ::= output <intexpr>
+// <output>    ::= output <expr>
 fn proc_output(state: &mut State) -> Command {
   eat(state,Token::Output);
-  let expr = proc_int_expr(state);
+  let expr = proc_expr(state);
 
-  return Command::Output(expr);
+  Command::Output(expr)
 }
 
 // <var>       ::= id
 fn proc_var(state: &mut State) -> Expression {
-  
-  let ex = Expression::Ex(Expr{expr: ExprType::Var,name: state.lexes[state.current].token.clone(), value: 0});
+  let line_number = state.lexes[state.current].line_number;
+
+  // Constants are replaced by their values
+  if is_qualified(state) {
+    let module = state.lexes[state.current].token.clone();
+    state.current += 2;
+    let name = state.lexes[state.current].token.clone();
+    eat(state,Token::Var);
+
+    return match state.program.modules[&module].constants.get(&name) {
+      Some(value) => constant(value,line_number),
+      None => show_semantic_error(line_number,&format!("Constante {}.{} não definida",module,name))
+    };
+  }
+  if let Some(value) = state.constants.get(&state.lexes[state.current].token) {
+    let ex = constant(value,line_number);
+
+    eat(state,Token::Var);
+    return ex;
+  }
+  let ex = Expression::Ex(Expr{expr: ExprType::Var,name: state.lexes[state.current].token.clone(), value: Value::Int(0), line_number});
 
   eat(state,Token::Var);
 
-  return ex;
+  ex
 }
 
-// <const>     ::= number
-fn proc_const(state: &mut State) -> Expression {
+fn constant(value: &Value, line_number: u16) -> Expression {
+  let expr = match value {
+    Value::Int(_) | Value::Big(_) => ExprType::ConstInt,
+    Value::Float(_) => ExprType::ConstFloat,
+    Value::Bool(_) => ExprType::ConstBool,
+    Value::Enum(_) => ExprType::ConstEnum,
+    _ => ExprType::ConstStr
+  };
+
+  Expression::Ex(Expr{expr,name: String::new(),value: value.clone(),line_number})
+}
+
+// <const>     ::= digits [ . digits ] [ (e | E) [ + | - ] digits ] | (0x | 0b | 0o) digits
+//
+// Digits may be separated by _, which the lexer leaves out. A negative literal is
+// checked as a whole, so the smallest integer of the width can be written.
+fn proc_const(state: &mut State, negative: bool) -> Expression {
+  let line_number = state.lexes[state.current].line_number;
   let tmp = state.lexes[state.current].token.clone();
+  let sign = if negative { "-" } else { "" };
 
   eat(state,Token::Number);
 
-  let value = tmp.parse::<i32>().expect("Bad number");
+  let radix = match tmp.get(..2) {
+    Some("0x") => 16,
+    Some("0b") => 2,
+    Some("0o") => 8,
+    _ => 10
+  };
+  if radix != 10 {
+    let digits = &tmp[2..];
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+      show_semantic_error(line_number,&format!("Número {}{} inválido",sign,tmp));
+    }
 
-  return Expression::Ex(Expr {expr: ExprType::ConstInt, value, name: String::new()});
-}
-  
-// <boolexpr>  ::= false | true |
-//                 not <boolexpr> |
-//                 <intterm> (== | != | < | > | <= | >=) <intterm>
-fn proc_bool_expr(state: &mut State, not: bool) -> Expression {
-  if matches!(&state.lexes[state.current].ttype, Token::False) {
-    state.current += 1;
-    let mut value = 0;
-    if not {value = !value}
-    
-    return Expression::Ex(Expr {expr: ExprType::ConstBool,value, name: String::new()});
-    
+    // Negative ones are the negated number instead of its bits
+    let value = if negative { integers::parse(&format!("-{}",digits),radix) } else { integers::parse_bits(digits,radix) };
+    let value = match value {
+      Some(value) => value,
+      None => show_semantic_error(line_number,&format!("Inteiro {}{} não cabe em {} bits",sign,tmp,integers::width()))
+    };
+    return Expression::Ex(Expr {expr: ExprType::ConstInt, value, name: String::new(), line_number});
   }
-  else if matches!(&state.lexes[state.current].ttype, Token::True) {
-    state.current += 1;
-    let mut  value = 1;
-    if not {value = !value}
-    
-    return Expression::Ex(Expr {expr: ExprType::ConstBool,value, name: String::new()});
-    
+
+  if tmp.contains(['.','e','E']) {
+    let value = format!("{}{}",sign,tmp).parse::<f64>().expect("Bad number");
+
+    return Expression::Ex(Expr {expr: ExprType::ConstFloat, value: Value::Float(value), name: String::new(), line_number});
   }
-  else if matches!(&state.lexes[state.current].ttype, Token::Not) {
-    state.current += 1;
-    return proc_bool_expr(state,true);
+
+  // Literals must fit in the width of the integers, unless they are unbounded
+  let tmp = format!("{}{}",sign,tmp);
+  let value = match integers::parse(&tmp,10) {
+    Some(value) => value,
+    None if integers::unsigned() => show_semantic_error(line_number,&format!("Inteiro {} não cabe em {} bits sem sinal",tmp,integers::width())),
+    None => show_semantic_error(line_number,&format!("Inteiro {} não cabe em {} bits",tmp,integers::width()))
+  };
+
+  Expression::Ex(Expr {expr: ExprType::ConstInt, value, name: String::new(), line_number})
+}
+
+// <string>    ::= "text"
+fn proc_string(state: &mut State) -> Expression {
+  let line_number = state.lexes[state.current].line_number;
+  let text = state.lexes[state.current].token.clone();
+
+  eat(state,Token::Str);
+
+  Expression::Ex(Expr {expr: ExprType::ConstStr, value: Value::Str(text), name: String::new(), line_number})
+}
+  
+// <expr>      ::= <binary>
+//
+// Whether the expression has the type its place requires is left to the type checker
+fn proc_expr(state: &mut State) -> Expression {
+  proc_binary(state,0)
+}
+
+enum BinaryOp {
+  Int(IntOp),
+  Bool(BoolOp),
+  Logic(LogicOp),
+}
+
+// Returns the operator, its precedence and whether it is right associative
+fn binary_op(ttype: Token) -> Option<(BinaryOp,u8,bool)> {
+  match ttype {
+    Token::Or => Some((BinaryOp::Logic(LogicOp::Or),1,false)),
+    Token::And => Some((BinaryOp::Logic(LogicOp::And),2,false)),
+    Token::Equal => Some((BinaryOp::Bool(BoolOp::Equal),4,false)),
+    Token::NotEqual => Some((BinaryOp::Bool(BoolOp::NotEqual),4,false)),
+    Token::Lower => Some((BinaryOp::Bool(BoolOp::Lower),4,false)),
+    Token::Greater => Some((BinaryOp::Bool(BoolOp::Greater),4,false)),
+    Token::LowerEqual => Some((BinaryOp::Bool(BoolOp::LowerEqual),4,false)),
+    Token::GreaterEqual => Some((BinaryOp::Bool(BoolOp::GreaterEqual),4,false)),
+    Token::BitOr => Some((BinaryOp::Int(IntOp::BitOr),5,false)),
+    Token::Xor => Some((BinaryOp::Int(IntOp::Xor),6,false)),
+    Token::BitAnd => Some((BinaryOp::Int(IntOp::BitAnd),7,false)),
+    Token::ShiftLeft => Some((BinaryOp::Int(IntOp::ShiftLeft),8,false)),
+    Token::ShiftRight => Some((BinaryOp::Int(IntOp::ShiftRight),8,false)),
+    Token::LogicalShiftRight => Some((BinaryOp::Int(IntOp::LogicalShiftRight),8,false)),
+    Token::Add => Some((BinaryOp::Int(IntOp::Add),9,false)),
+    Token::Sub => Some((BinaryOp::Int(IntOp::Sub),9,false)),
+    Token::Mul => Some((BinaryOp::Int(IntOp::Mul),10,false)),
+    Token::Div => Some((BinaryOp::Int(IntOp::Div),10,false)),
+    Token::RatDiv => Some((BinaryOp::Int(IntOp::Ratio),10,false)),
+    Token::Mod => Some((BinaryOp::Int(IntOp::Mod),10,false)),
+    Token::Pow => Some((BinaryOp::Int(IntOp::Pow),12,true)),
+    _ => None
   }
-  else {
-    let mut bool_op: BoolOp = BoolOp::Equal;
-    let left = proc_int_term(state);
+}
 
-    match state.lexes[state.current].ttype {
-      Token::Equal => {
-        bool_op = BoolOp::Equal;
-      },
-      Token::NotEqual => {
-        bool_op = BoolOp::NotEqual;
-      },
-      Token::Lower => {
-        bool_op = BoolOp::Lower;
-      },
-      Token::Greater => {
-        bool_op = BoolOp::Greater;
-      },
-      Token::GreaterEqual => {
-        bool_op = BoolOp::GreaterEqual;
-      },
-      Token::LowerEqual => {
-        bool_op = BoolOp::LowerEqual;
-      },
-      _ => show_error(&state.lexes[state.current])
+const NOT_PRECEDENCE: u8 = 3;
+const UNARY_PRECEDENCE: u8 = 11;
+
+// <binary>    ::= <unary> { <binop> <unary> }
+// <binop>     ::= or | and | == | != | < | > | <= | >= | '|' | xor | & | << | >> | >>> |
+//                 + | - | * | / | // | % | ^
+//
+// Parsed by precedence climbing. From lowest to highest:
+//   or                    left associative
+//   and                   left associative
+//   not
+//   == != < > <= >=       non associative
+//   |                     left associative
+//   xor                   left associative
+//   &                     left associative
+//   << >> >>>             left associative
+//   + -                   left associative
+//   * / // %              left associative
+//   unary + - ~
+//   ^                     right associative
+fn proc_binary(state: &mut State, min_precedence: u8) -> Expression {
+  let mut left = proc_unary(state);
+
+  while let Some((op,precedence,right_assoc)) = binary_op(state.lexes[state.current].ttype) {
+    if precedence < min_precedence {
+      break;
     }
+    let line = state.lexes[state.current].line_number;
     state.current += 1;
-    let right = proc_int_term(state);
 
-    if let Expression::Ex(l) = left {
-      if let Expression::Ex(r) = right{
-        return Expression::Sb(SingleBool{left:l,right:r,op: bool_op});
-      }
+    let next_precedence = if right_assoc {precedence} else {precedence + 1};
+    let right = proc_binary(state,next_precedence);
+
+    // Comparisons are non associative, a < b < c needs parentheses
+    let chained = binary_op(state.lexes[state.current].ttype).is_some_and(|(next,_,_)| matches!(next, BinaryOp::Bool(_)));
+    if matches!(op, BinaryOp::Bool(_)) && chained {
+      show_semantic_error(state.lexes[state.current].line_number,"Comparações não podem ser encadeadas, use parênteses");
     }
 
-    panic!("Bad bool expressions");
+    left = match op {
+      BinaryOp::Int(op) => Expression::Bi(BinaryInt{left: Box::new(left),op,right: Box::new(right),line_number: line}),
+      BinaryOp::Bool(op) => Expression::Sb(SingleBool{left: Box::new(left),op,right: Box::new(right),line_number: line}),
+      BinaryOp::Logic(op) => Expression::Bb(BinaryBool{left: Box::new(left),op,right: Box::new(right),line_number: line}),
+    };
   }
+
+  left
 }
-  
-// <intexpr>   ::= [ + | - ] <intterm> [ (+ | - | * | / | % | ^) <intterm> ]
-fn proc_int_expr(state: &mut State) -> Expression {
-  let mut negative = false;
 
-  if matches!(&state.lexes[state.current].ttype, Token::Add){
+// <unary>     ::= (+ | - | ~ | not) <unary> | <term>
+fn proc_unary(state: &mut State) -> Expression {
+  let line = state.lexes[state.current].line_number;
+
+  if matches!(state.lexes[state.current].ttype, Token::Add) {
     state.current += 1;
+    let expr = proc_binary(state,UNARY_PRECEDENCE);
+
+    return expr;
   }
-  else if matches!(&state.lexes[state.current].ttype, Token::Sub) {
+  else if matches!(state.lexes[state.current].ttype, Token::Sub) {
     state.current += 1;
-    negative = true;
+
+    // A number right after the sign is a negative literal, unless ^ takes it first
+    let literal = matches!(state.lexes[state.current].ttype, Token::Number)
+      && !state.lexes.get(state.current + 1).is_some_and(|lex| matches!(lex.ttype, Token::Pow | Token::LBracket | Token::Dot));
+    if literal {
+      return proc_const(state,true);
+    }
+
+    let expr = proc_binary(state,UNARY_PRECEDENCE);
+
+    return Expression::Un(UnaryInt{op: UnaryOp::Neg,expr: Box::new(expr),line_number: line});
   }
+  else if matches!(state.lexes[state.current].ttype, Token::BitNot) {
+    state.current += 1;
+    let expr = proc_binary(state,UNARY_PRECEDENCE);
 
-  let mut term = proc_int_term(state);
+    return Expression::Un(UnaryInt{op: UnaryOp::BitNot,expr: Box::new(expr),line_number: line});
+  }
+  else if matches!(state.lexes[state.current].ttype, Token::Not) {
+    state.current += 1;
+    let expr = proc_binary(state,NOT_PRECEDENCE + 1);
 
-  if negative {
-    if let Expression::Ex(ref mut e) = term {
-      e.expr = ExprType::NegInt;
-    }
-    else {
-      panic!("Can't invert int term (Bad term)");
-    }
+    return Expression::Nb(NotBool{expr: Box::new(expr),line_number: line});
   }
 
-  if  matches!(&state.lexes[state.current].ttype, Token::Add) ||
-    matches!(&state.lexes[state.current].ttype, Token::Sub) ||
-    matches!(&state.lexes[state.current].ttype, Token::Mul) ||
-    matches!(&state.lexes[state.current].ttype, Token::Div) ||
-    matches!(&state.lexes[state.current].ttype, Token::Mod) ||
-    matches!(&state.lexes[state.current].ttype, Token::Pow) 
-  {
+  proc_term(state)
+}
+  
+// <term>      ::= <primary> { [ <expr> ] | . <var> }
+fn proc_term(state: &mut State) -> Expression{
+  let mut term = proc_primary(state);
 
-    let mut op: IntOp = IntOp::Add;
+  loop {
+    let line_number = state.lexes[state.current].line_number;
 
     match state.lexes[state.current].ttype {
-      Token::Add => {
-        op = IntOp::Add
-      },
-      Token::Sub => {
-        op = IntOp::Sub
-      },
-      Token::Mul => {
-        op = IntOp::Mul
-      },
-      Token::Div => {
-        op = IntOp::Div
+      Token::LBracket => {
+        state.current += 1;
+
+        let index = proc_expr(state);
+        eat(state,Token::RBracket);
+
+        term = Expression::Ix(Index{array: Box::new(term),index: Box::new(index),line_number});
       },
-      Token::Mod => {
-        op = IntOp::Mod
+      Token::Dot => {
+        let name = proc_field(state);
+
+        term = Expression::Fd(Field{record: Box::new(term),name,line_number});
       },
-      Token::Pow => {
-        op = IntOp::Pow
-      }
-      _ => show_error(&state.lexes[state.current])
+      _ => return term
     }
+  }
+}
 
-    state.current += 1;
+// <primary>   ::= <var> | <call> | <const> | <string> | read | true | false | ( <expr> ) | <array> |
+//                 <map> | <condexpr>
+// <array>     ::= [ [ <expr> { , <expr> } ] ]
+// <map>       ::= { [ <expr> : <expr> { , <expr> : <expr> } ] }
+// <condexpr>  ::= if <expr> then <expr> else <expr>
+fn proc_primary(state: &mut State) -> Expression{
+  let line_number = state.lexes[state.current].line_number;
+
+  match state.lexes[state.current].ttype {
+    Token::Var if is_call(state) => proc_call(state),
+    Token::Var => proc_var(state),
+    Token::Number => proc_const(state,false),
+    Token::Str => proc_string(state),
+    Token::True | Token::False => {
+      let value = matches!(state.lexes[state.current].ttype, Token::True);
+      state.current += 1;
+
+      Expression::Ex(Expr {expr: ExprType::ConstBool,value: Value::Bool(value), name: String::new(), line_number})
+    },
+    Token::LParen => {
+      state.current += 1;
+      let expr = proc_expr(state);
+      eat(state,Token::RParen);
+
+      expr
+    },
+    Token::LBracket => {
+      state.current += 1;
+
+      let mut items = Vec::<Expression>::new();
+      if !matches!(state.lexes[state.current].ttype, Token::RBracket) {
+        items.push(proc_expr(state));
+
+        while matches!(state.lexes[state.current].ttype, Token::Comma) {
+          state.current += 1;
+          items.push(proc_expr(state));
+        }
+      }
+      eat(state,Token::RBracket);
+
+      Expression::Al(ArrayLit{items,line_number})
+    },
+    Token::LBrace => {
+      state.current += 1;
 
-    let right = proc_int_term(state);
+      let mut entries = Vec::<(Expression,Expression)>::new();
+      while !matches!(state.lexes[state.current].ttype, Token::RBrace) {
+        if !entries.is_empty() {
+          eat(state,Token::Comma);
+        }
 
-    if let Expression::Ex(ref l) = term {
-      if let Expression::Ex(ref r) = right{
-        return Expression::Bi( BinaryInt{left:l.clone(),right:r.clone(),op} );
+        let key = proc_expr(state);
+        eat(state,Token::Colon);
+        entries.push((key,proc_expr(state)));
       }
+      eat(state,Token::RBrace);
+
+      Expression::Ml(MapLit{entries,line_number})
+    },
+    Token::If => {
+      state.current += 1;
+      let cond = proc_expr(state);
+
+      eat(state,Token::Then);
+      let then_expr = proc_expr(state);
+
+      eat(state,Token::Else);
+      let else_expr = proc_expr(state);
+
+      Expression::Cd(Conditional{
+        cond: Box::new(cond),
+        then_expr: Box::new(then_expr),
+        else_expr: Box::new(else_expr),
+        line_number
+      })
+    },
+    _ => {
+      eat(state,Token::Read);
+
+      Expression::Ex(Expr {expr: ExprType::ReadInt,value: Value::Int(0), name: String::new(), line_number})
     }
   }
-  return term;
-}
-  
-// <intterm>   ::= <var> | <const> | read
-fn proc_int_term(state: &mut State) -> Expression{
-  if matches!(state.lexes[state.current].ttype, Token::Var){
-    return proc_var(state);
-  }
-  else if matches!(state.lexes[state.current].ttype, Token::Number){
-    return proc_const(state);
-  }
-  else {
-    eat(state,Token::Read);
-    
-    return Expression::Ex(Expr {expr: ExprType::ReadInt,value:0, name: String::new()});
-  }
 }
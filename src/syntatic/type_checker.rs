@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::lexical::sources;
+
+use crate::interpreter::expressions::Expression;
+use crate::interpreter::expressions::ExprType;
+use crate::interpreter::expressions::BoolOp;
+use crate::interpreter::expressions::IntOp;
+use crate::interpreter::expressions::UnaryOp;
+use crate::interpreter::expressions::BuiltinFunc;
+use crate::interpreter::values::Value;
+use crate::interpreter::errors::ERROR_RECORD;
+
+use crate::interpreter::commands::Command;
+use crate::interpreter::commands::AssignCmd;
+use crate::interpreter::commands::Accessor;
+use crate::interpreter::commands::Function;
+
+// Static type of an expression. Any is given to what can only be known while
+// running (read, function calls, array elements), it is accepted everywhere
+// and checked again by the interpreter.
+#[derive (Clone, PartialEq)]
+pub enum Type {
+  Int,
+  Rational,
+  Float,
+  Bool,
+  Str,
+  Array,
+  Map,
+  // Values of the record or enum with that name
+  Record(String),
+  Enum(String),
+  Any
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self,f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Type::Int => write!(f,"int"),
+      Type::Rational => write!(f,"rational"),
+      Type::Float => write!(f,"float"),
+      Type::Bool => write!(f,"bool"),
+      Type::Str => write!(f,"string"),
+      Type::Array => write!(f,"array"),
+      Type::Map => write!(f,"map"),
+      Type::Record(name) | Type::Enum(name) => write!(f,"{}",name),
+      Type::Any => write!(f,"?")
+    }
+  }
+}
+
+impl Type {
+  fn is_number(&self) -> bool {
+    matches!(self, Type::Int | Type::Rational | Type::Float | Type::Any)
+  }
+
+  fn accepts(&self, other: &Type) -> bool {
+    self == other || *self == Type::Any || *other == Type::Any
+  }
+}
+
+// Mirrors the scopes the interpreter creates for a function or the program
+struct Env<'a> {
+  // Types of the variables seen so far, one map for each block being checked
+  scopes: Vec::<HashMap::<String,Type>>,
+  // Every variable assigned somewhere in the function or program
+  assigned: HashSet::<String>,
+  // Field names of every record
+  records: &'a HashMap::<String,Vec::<String>>,
+}
+
+impl Env<'_> {
+  fn new(records: &HashMap::<String,Vec::<String>>) -> Env<'_> {
+    Env {scopes: vec![HashMap::new()],assigned: HashSet::new(),records}
+  }
+
+  fn get(&self, var: &str) -> Option<&Type> {
+    self.scopes.iter().rev().find_map(|scope| scope.get(var))
+  }
+}
+
+fn type_error(line: u16, msg: &str) -> ! {
+  println!("{}:: {}",sources::location(line),msg);
+  std::process::exit(1);
+}
+
+// Checks the program and every function before anything runs, stopping at the first error
+pub fn check(program: &[Command], functions: &HashMap::<String,Rc<Function>>, records: &HashMap::<String,Vec::<String>>){
+  let mut names: Vec::<&String> = functions.keys().collect();
+  names.sort();
+
+  for name in names {
+    let function = &functions[name];
+    let mut env = Env::new(records);
+
+    for param in &function.params {
+      env.scopes[0].insert(param.clone(),Type::Any);
+    }
+    collect_assigned(&function.body,&mut env.assigned);
+    check_cmds(&function.body,&mut env);
+  }
+
+  let mut env = Env::new(records);
+  collect_assigned(program,&mut env.assigned);
+  check_cmds(program,&mut env);
+}
+
+fn collect_assigned(cmds: &[Command], assigned: &mut HashSet::<String>){
+  for cmd in cmds {
+    match cmd {
+      Command::Assignment(asg) => {
+        assigned.insert(asg.var.clone());
+      },
+      Command::MultiAssignment(asgs) => {
+        for asg in asgs {
+          assigned.insert(asg.var.clone());
+        }
+      },
+      Command::Block(b) => collect_assigned(b,assigned),
+      Command::If(i) => {
+        collect_assigned(&i.then_cmds,assigned);
+        for elif in &i.elifs {
+          collect_assigned(&elif.cmds,assigned);
+        }
+        collect_assigned(&i.else_cmds,assigned);
+      },
+      Command::Case(c) => {
+        for arm in &c.arms {
+          collect_assigned(&arm.cmds,assigned);
+        }
+        collect_assigned(&c.else_cmds,assigned);
+      },
+      Command::While(w) => collect_assigned(&w.cmds,assigned),
+      Command::For(f) => collect_assigned(&f.cmds,assigned),
+      Command::ForIn(f) => collect_assigned(&f.cmds,assigned),
+      Command::Repeat(r) => collect_assigned(&r.cmds,assigned),
+      Command::Try(t) => {
+        collect_assigned(&t.body,assigned);
+        collect_assigned(&t.handler,assigned);
+        collect_assigned(&t.finally,assigned);
+      },
+      Command::Declaration(_) | Command::Output(_) | Command::Call(_) | Command::Return(_) |
+      Command::Break(_) | Command::Continue(_) | Command::Raise(_) | Command::Check(_) => {}
+    }
+  }
+}
+
+fn check_block(cmds: &[Command], env: &mut Env){
+  env.scopes.push(HashMap::new());
+  check_cmds(cmds,env);
+  env.scopes.pop();
+}
+
+fn check_cmds(cmds: &[Command], env: &mut Env){
+  for cmd in cmds {
+    match cmd {
+      Command::Assignment(asg) => {
+        let value = type_of(&asg.expr,env);
+        check_assign(asg,value,env);
+      },
+      Command::MultiAssignment(asgs) => {
+        // All values are computed before the first variable changes
+        let values: Vec::<Type> = asgs.iter().map(|asg| type_of(&asg.expr,env)).collect();
+
+        for (asg,value) in asgs.iter().zip(values) {
+          check_assign(asg,value,env);
+        }
+      },
+      Command::Declaration(d) => {
+        let value = type_of(&d.expr,env);
+        let scope = env.scopes.last_mut().expect("No scope");
+
+        if scope.contains_key(&d.var) {
+          type_error(d.line_number,&format!("Variável {} já declarada neste bloco",d.var));
+        }
+        scope.insert(d.var.clone(),value);
+      },
+      Command::Block(b) => check_cmds(b,env),
+      Command::If(i) => {
+        expect(&i.condition,Type::Bool,env);
+        check_block(&i.then_cmds,env);
+        for elif in &i.elifs {
+          expect(&elif.condition,Type::Bool,env);
+          check_block(&elif.cmds,env);
+        }
+        check_block(&i.else_cmds,env);
+      },
+      Command::Case(c) => {
+        let kind = match &c.kind {
+          Some(ty) => Type::Enum(ty.clone()),
+          None => Type::Int
+        };
+        expect(&c.expr,kind,env);
+        for arm in &c.arms {
+          check_block(&arm.cmds,env);
+        }
+        check_block(&c.else_cmds,env);
+      },
+      Command::While(w) => {
+        expect(&w.cond,Type::Bool,env);
+        for invariant in &w.invariants {
+          expect(&invariant.cond,Type::Bool,env);
+        }
+        check_block(&w.cmds,env);
+      },
+      Command::For(f) => {
+        expect(&f.start,Type::Int,env);
+        expect(&f.end,Type::Int,env);
+        expect(&f.step,Type::Int,env);
+
+        env.scopes.push(HashMap::from([(f.var.clone(),Type::Int)]));
+        check_block(&f.cmds,env);
+        env.scopes.pop();
+      },
+      Command::ForIn(f) => {
+        let collection = type_of(&f.expr,env);
+        if !matches!(collection, Type::Array | Type::Map | Type::Any) {
+          type_error(f.line_number,&format!("Não é possível percorrer um valor do tipo {}",collection));
+        }
+
+        env.scopes.push(HashMap::from([(f.var.clone(),Type::Any)]));
+        check_block(&f.cmds,env);
+        env.scopes.pop();
+      },
+      Command::Repeat(r) => {
+        env.scopes.push(HashMap::new());
+        check_cmds(&r.cmds,env);
+        expect(&r.cond,Type::Bool,env);
+        env.scopes.pop();
+      },
+      Command::Check(c) => {
+        expect(&c.cond,Type::Bool,env);
+      },
+      Command::Output(e) | Command::Call(e) | Command::Return(e) => {
+        type_of(e,env);
+      },
+      Command::Try(t) => {
+        check_block(&t.body,env);
+
+        env.scopes.push(HashMap::from([(t.var.clone(),Type::Record(ERROR_RECORD.to_string()))]));
+        check_block(&t.handler,env);
+        env.scopes.pop();
+
+        check_block(&t.finally,env);
+      },
+      Command::Raise(r) => {
+        let error = type_of(r,env);
+
+        if !Type::Int.accepts(&error) && error != Type::Record(ERROR_RECORD.to_string()) {
+          type_error(r.line(),&format!("Raise espera um código inteiro ou um erro, encontrado {}",error));
+        }
+      },
+      Command::Break(_) | Command::Continue(_) => {}
+    }
+  }
+}
+
+fn check_assign(asg: &AssignCmd, value: Type, env: &mut Env){
+  if asg.path.is_empty() {
+    let value = match asg.op {
+      Some(op) => {
+        let current = var_type(&asg.var,asg.line_number,env);
+        arith_type(op,current,value,asg.line_number)
+      },
+      None => value
+    };
+    assign(env,&asg.var,value,asg.line_number);
+    return;
+  }
+
+  // Follows the path from the variable, array elements and fields have no known type
+  let mut target = env.get(&asg.var).cloned().unwrap_or(Type::Any);
+  for accessor in &asg.path {
+    target = match accessor {
+      Accessor::Index(index) => index_type(&target,index,asg.line_number,env),
+      Accessor::Field(name) => field_type(&target,name,asg.line_number,env)
+    };
+  }
+
+  if let Some(op) = asg.op {
+    arith_type(op,target,value,asg.line_number);
+  }
+}
+
+// Arrays are indexed by integers, maps by any key
+fn index_type(collection: &Type, index: &Expression, line: u16, env: &Env) -> Type {
+  match collection {
+    Type::Array => {
+      expect(index,Type::Int,env);
+    },
+    Type::Map | Type::Any => {
+      type_of(index,env);
+    },
+    _ => type_error(line,&format!("Valor do tipo {} não pode ser indexado",collection))
+  }
+
+  Type::Any
+}
+
+fn field_type(record: &Type, field: &str, line: u16, env: &Env) -> Type {
+  match record {
+    Type::Record(name) if !env.records[name].iter().any(|f| f == field) => {
+      type_error(line,&format!("Registro {} não tem o campo {}",name,field))
+    },
+    Type::Record(_) | Type::Any => Type::Any,
+    _ => type_error(line,&format!("Valor do tipo {} não tem campos",record))
+  }
+}
+
+// A variable keeps the type of its first assignment, ints may be stored in float variables.
+// Like in the interpreter, assigning to a variable that is not visible creates it in the first scope.
+fn assign(env: &mut Env, var: &str, value: Type, line: u16){
+  let scope = match env.scopes.iter_mut().rev().find(|scope| scope.contains_key(var)) {
+    Some(scope) => scope,
+    None => &mut env.scopes[0]
+  };
+
+  match scope.get(var) {
+    Some(Type::Any) | None => {
+      scope.insert(var.to_string(),value);
+    },
+    Some(current) => {
+      let widening = (*current == Type::Float && matches!(value, Type::Int | Type::Rational)) ||
+        (*current == Type::Rational && value == Type::Int);
+
+      if !current.accepts(&value) && !widening {
+        type_error(line,&format!("Variável {} do tipo {} não pode receber {}",var,current,value));
+      }
+    }
+  }
+}
+
+fn expect(expr: &Expression, expected: Type, env: &Env) -> Type {
+  let found = type_of(expr,env);
+
+  if !expected.accepts(&found) {
+    type_error(expr.line(),&format!("Esperada expressão do tipo {}, encontrada {}",expected,found));
+  }
+
+  found
+}
+
+fn var_type(var: &str, line: u16, env: &Env) -> Type {
+  match env.get(var) {
+    Some(var) => var.clone(),
+    None if env.assigned.contains(var) => Type::Any,
+    None => type_error(line,&format!("Variável {} não definida",var))
+  }
+}
+
+fn arith_type(op: IntOp, left: Type, right: Type, line: u16) -> Type {
+  if matches!(op, IntOp::Add) && (left == Type::Str || right == Type::Str) &&
+    Type::Str.accepts(&left) && Type::Str.accepts(&right) {
+    return Type::Str;
+  }
+
+  if op.is_bitwise() {
+    if !Type::Int.accepts(&left) || !Type::Int.accepts(&right) {
+      type_error(line,&format!("Operação bit a bit inválida entre {} e {}",left,right));
+    }
+    return Type::Int;
+  }
+
+  if !left.is_number() || !right.is_number() {
+    type_error(line,&format!("Operação aritmética inválida entre {} e {}",left,right));
+  }
+
+  if left == Type::Float || right == Type::Float {
+    Type::Float
+  }
+  else if left == Type::Any || right == Type::Any {
+    Type::Any
+  }
+  else if matches!(op, IntOp::Ratio) || left == Type::Rational || right == Type::Rational {
+    Type::Rational
+  }
+  else {
+    Type::Int
+  }
+}
+
+fn type_of(expr: &Expression, env: &Env) -> Type {
+  match expr {
+    Expression::Ex(ex) => match ex.expr {
+      ExprType::ConstInt => Type::Int,
+      ExprType::ConstFloat => Type::Float,
+      ExprType::ConstStr => Type::Str,
+      ExprType::ConstBool => Type::Bool,
+      ExprType::ConstEnum => match &ex.value {
+        Value::Enum(variant) => Type::Enum(variant.ty.clone()),
+        _ => unreachable!()
+      },
+      ExprType::ReadInt => Type::Any,
+      ExprType::Var => var_type(&ex.name,ex.line_number,env)
+    },
+    Expression::Un(un) => {
+      let value = type_of(&un.expr,env);
+
+      if matches!(un.op, UnaryOp::BitNot) && !Type::Int.accepts(&value) {
+        type_error(un.line_number,&format!("Operação bit a bit inválida sobre {}",value));
+      }
+      if !value.is_number() {
+        type_error(un.line_number,&format!("Operação aritmética inválida sobre {}",value));
+      }
+      value
+    },
+    Expression::Bi(bi) => {
+      let left = type_of(&bi.left,env);
+      let right = type_of(&bi.right,env);
+      arith_type(bi.op,left,right,bi.line_number)
+    },
+    Expression::Sb(sb) => {
+      let left = type_of(&sb.left,env);
+      let right = type_of(&sb.right,env);
+
+      let comparable = match sb.op {
+        BoolOp::Equal | BoolOp::NotEqual => left.accepts(&right) || (left.is_number() && right.is_number()),
+        _ => (left.is_number() && right.is_number()) ||
+          (Type::Str.accepts(&left) && Type::Str.accepts(&right))
+      };
+
+      if !comparable {
+        type_error(sb.line_number,&format!("Comparação inválida entre {} e {}",left,right));
+      }
+      Type::Bool
+    },
+    Expression::Bb(bb) => {
+      expect(&bb.left,Type::Bool,env);
+      expect(&bb.right,Type::Bool,env);
+      Type::Bool
+    },
+    Expression::Nb(nb) => {
+      expect(&nb.expr,Type::Bool,env);
+      Type::Bool
+    },
+    Expression::Fc(fc) => {
+      for arg in &fc.args {
+        type_of(arg,env);
+      }
+      Type::Any
+    },
+    Expression::Bt(bt) => {
+      let args: Vec::<Type> = bt.args.iter().map(|arg| type_of(arg,env)).collect();
+      let invalid = |arg: &Type| type_error(bt.line_number,&format!("Argumento do tipo {} inválido",arg));
+
+      match bt.func {
+        BuiltinFunc::Array => {
+          if !Type::Int.accepts(&args[0]) { invalid(&args[0]) }
+          Type::Array
+        },
+        BuiltinFunc::Len => {
+          if !matches!(args[0], Type::Array | Type::Map | Type::Str | Type::Any) { invalid(&args[0]) }
+          Type::Int
+        },
+        BuiltinFunc::Substr => {
+          if !Type::Str.accepts(&args[0]) { invalid(&args[0]) }
+          if !Type::Int.accepts(&args[1]) { invalid(&args[1]) }
+          if !Type::Int.accepts(&args[2]) { invalid(&args[2]) }
+          Type::Str
+        },
+        BuiltinFunc::Str => Type::Str,
+        BuiltinFunc::Int => {
+          if !args[0].is_number() && args[0] != Type::Str { invalid(&args[0]) }
+          Type::Int
+        },
+        BuiltinFunc::Rational => {
+          if !args[0].is_number() { invalid(&args[0]) }
+          if !args[1].is_number() { invalid(&args[1]) }
+          arith_type(IntOp::Ratio,args[0].clone(),args[1].clone(),bt.line_number)
+        },
+        BuiltinFunc::Float => {
+          if !args[0].is_number() && args[0] != Type::Str { invalid(&args[0]) }
+          Type::Float
+        },
+        BuiltinFunc::Has => {
+          if !Type::Map.accepts(&args[0]) { invalid(&args[0]) }
+          Type::Bool
+        },
+        BuiltinFunc::Remove => {
+          if !Type::Map.accepts(&args[0]) { invalid(&args[0]) }
+          Type::Any
+        },
+        BuiltinFunc::Keys => {
+          if !Type::Map.accepts(&args[0]) { invalid(&args[0]) }
+          Type::Array
+        }
+      }
+    },
+    Expression::Al(al) => {
+      for item in &al.items {
+        type_of(item,env);
+      }
+      Type::Array
+    },
+    Expression::Ix(ix) => {
+      let collection = type_of(&ix.array,env);
+      index_type(&collection,&ix.index,ix.line_number,env)
+    },
+    Expression::Ml(ml) => {
+      for (key,value) in &ml.entries {
+        type_of(key,env);
+        type_of(value,env);
+      }
+      Type::Map
+    },
+    Expression::Rl(rl) => {
+      for arg in &rl.args {
+        type_of(arg,env);
+      }
+      Type::Record(rl.name.clone())
+    },
+    Expression::Fd(fd) => {
+      let record = type_of(&fd.record,env);
+      field_type(&record,&fd.name,fd.line_number,env)
+    },
+    Expression::Cd(cd) => {
+      expect(&cd.cond,Type::Bool,env);
+      let then_type = type_of(&cd.then_expr,env);
+      let else_type = type_of(&cd.else_expr,env);
+
+      if then_type == else_type {
+        then_type
+      }
+      else if then_type == Type::Any || else_type == Type::Any {
+        Type::Any
+      }
+      else if matches!(then_type, Type::Int | Type::Rational) && matches!(else_type, Type::Int | Type::Rational) {
+        Type::Rational
+      }
+      else if then_type.is_number() && else_type.is_number() {
+        Type::Float
+      }
+      else {
+        type_error(cd.line_number,&format!("Ramos do if com tipos diferentes: {} e {}",then_type,else_type))
+      }
+    }
+  }
+}